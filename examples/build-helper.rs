@@ -1,13 +1,6 @@
 use rust_tracing::trace;
-use std::sync::{
-    Arc,
-    atomic::AtomicBool,
-};
 
-fn main() {
-    let term: Arc<AtomicBool> = Default::default();
-
-    let _ = signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&term));
-
-    trace();
+#[tokio::main]
+async fn main() {
+    trace().install_shutdown_handler();
 }
@@ -5,7 +5,8 @@
 //! - 1 span every 5 seconds
 //! - 1 event every 5 seconds
 //!
-//! It can be killed via sigint or sigterm
+//! It can be killed via sigint or sigterm, flushing the exports before
+//! exiting.
 
 use rust_tracing::{
     deps::tracing::{
@@ -14,25 +15,15 @@ use rust_tracing::{
     },
     trace,
 };
-use std::sync::{
-    Arc,
-    atomic::{
-        AtomicBool,
-        Ordering,
-    },
-};
 
 #[tokio::main]
-async fn main() -> Result<(), std::io::Error> {
-    let term: Arc<AtomicBool> = Default::default();
-    signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&term))?;
-    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&term))?;
+async fn main() {
+    trace().install_shutdown_handler();
 
-    let _guard = trace();
     let mut counter = 0;
     let _outer = info_span!("outer span").entered();
 
-    while !term.load(Ordering::Relaxed) {
+    loop {
         let _inner = info_span!("inner span").entered();
 
         tokio::time::sleep(std::time::Duration::from_secs(5)).await;
@@ -40,8 +31,4 @@ async fn main() -> Result<(), std::io::Error> {
         counter += 1;
         info!(counter, "this is an event");
     }
-
-    info!("signal received, shutting down");
-
-    Ok(())
 }
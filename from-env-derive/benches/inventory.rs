@@ -0,0 +1,43 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use init4_bin_base::utils::from_env::FromEnv;
+
+#[derive(Debug, FromEnv)]
+pub struct Leaf {
+    #[from_env(var = "BENCH_A", desc = "A")]
+    pub a: u8,
+    #[from_env(var = "BENCH_B", desc = "B", infallible)]
+    pub b: String,
+    #[from_env(var = "BENCH_C", desc = "C", optional, infallible)]
+    pub c: Option<String>,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct Composite {
+    pub first: Leaf,
+    pub second: Leaf,
+    pub third: Leaf,
+    #[from_env(var = "BENCH_D", desc = "D")]
+    pub d: u64,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct Generic<T> {
+    pub inner: T,
+    #[from_env(var = "BENCH_E", desc = "E")]
+    pub e: u64,
+}
+
+fn inventory(c: &mut Criterion) {
+    let mut group = c.benchmark_group("inventory");
+    group.bench_function("built", |b| b.iter(|| black_box(Composite::inventory())));
+    group.bench_function("memoized", |b| {
+        b.iter(|| black_box(Composite::cached_inventory()))
+    });
+    group.bench_function("cached per type", |b| {
+        b.iter(|| black_box(Generic::<Composite>::cached_inventory()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, inventory);
+criterion_main!(benches);
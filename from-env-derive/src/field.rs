@@ -1,8 +1,52 @@
 use heck::ToPascalCase;
-use proc_macro2::TokenStream;
+use proc_macro2::{TokenStream, TokenTree};
 use quote::quote;
 use syn::{spanned::Spanned, Ident, LitStr};
 
+/// Returns true if the tokens mention any of the given identifiers, e.g. if a
+/// type mentions any of the generic parameters of the struct.
+pub(crate) fn mentions_any(tokens: TokenStream, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions_any(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Joins the lines of the doc comment of a field, if any, into a single line.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(line),
+                        ..
+                    }),
+                ..
+            }) => Some(line.value().trim().to_owned()),
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+/// Returns true if the type is a `Redacted`, e.g. `Redacted<String>`.
+fn is_redacted(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Redacted"),
+        _ => false,
+    }
+}
+
 /// A parsed Field of a struct
 pub(crate) struct Field {
     env_var: Option<LitStr>,
@@ -10,8 +54,16 @@ pub(crate) struct Field {
     field_type: syn::Type,
 
     optional: bool,
+    secret: bool,
     infallible: bool,
     skip: bool,
+    json: bool,
+    allow_empty: bool,
+    delimiter: Option<LitStr>,
+    group: Option<LitStr>,
+    requires: Vec<LitStr>,
+    aliases: Vec<LitStr>,
+    deprecated: Option<LitStr>,
     desc: Option<String>,
 
     _attrs: Vec<syn::Attribute>,
@@ -24,10 +76,18 @@ impl TryFrom<&syn::Field> for Field {
 
     fn try_from(field: &syn::Field) -> Result<Self, syn::Error> {
         let mut optional = false;
+        let mut secret = false;
         let mut env_var = None;
         let mut infallible = false;
         let mut desc = None;
         let mut skip = false;
+        let mut json = false;
+        let mut allow_empty = false;
+        let mut delimiter = None;
+        let mut group = None;
+        let mut requires = vec![];
+        let mut aliases = vec![];
+        let mut deprecated = None;
 
         field
             .attrs
@@ -43,6 +103,10 @@ impl TryFrom<&syn::Field> for Field {
                         optional = true;
                         return Ok(());
                     }
+                    if meta.path.is_ident("secret") {
+                        secret = true;
+                        return Ok(());
+                    }
                     if meta.path.is_ident("var") {
                         env_var = Some(meta.value()?.parse::<LitStr>()?);
                         return Ok(());
@@ -51,6 +115,34 @@ impl TryFrom<&syn::Field> for Field {
                         desc = Some(meta.value()?.parse::<LitStr>()?.value());
                         return Ok(());
                     }
+                    if meta.path.is_ident("json") {
+                        json = true;
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("allow_empty") {
+                        allow_empty = true;
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("delimiter") {
+                        delimiter = Some(meta.value()?.parse::<LitStr>()?);
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("group") {
+                        group = Some(meta.value()?.parse::<LitStr>()?);
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("requires") {
+                        requires.push(meta.value()?.parse::<LitStr>()?);
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("alias") {
+                        aliases.push(meta.value()?.parse::<LitStr>()?);
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("deprecated") {
+                        deprecated = Some(meta.value()?.parse::<LitStr>()?);
+                        return Ok(());
+                    }
                     if meta.path.is_ident("infallible") {
                         infallible = true;
                     }
@@ -58,14 +150,83 @@ impl TryFrom<&syn::Field> for Field {
                 });
             });
 
+        if desc.is_none() {
+            desc = doc_comment(&field.attrs);
+        }
+
         if desc.is_none() && env_var.is_some() {
             return Err(syn::Error::new(
                 field.span(),
-                "Missing description for field. Use `#[from_env(desc = \"DESC\")]`",
+                "Missing description for field. Use `#[from_env(desc = \"DESC\")]` or a doc comment",
+            ));
+        }
+
+        if env_var.is_some() && desc.as_deref().is_some_and(|desc| desc.trim().is_empty()) {
+            return Err(syn::Error::new(
+                field.span(),
+                "Empty description for field. Use `#[from_env(desc = \"DESC\")]` with a non-empty description",
+            ));
+        }
+
+        if json && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for json field. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if allow_empty && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for allow_empty field. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if delimiter.is_some() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for delimited field. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if group.is_some() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for grouped field. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if !requires.is_empty() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for field with requirements. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if !aliases.is_empty() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for aliased field. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if deprecated.is_some() && env_var.is_none() {
+            return Err(syn::Error::new(
+                field.span(),
+                "Missing env var for deprecated field. Use `#[from_env(var = \"VAR\")]`",
+            ));
+        }
+
+        if delimiter.is_some() && json {
+            return Err(syn::Error::new(
+                field.span(),
+                "Fields cannot be both json and delimited",
             ));
         }
 
         let field_type = field.ty.clone();
+        // `Redacted` props hold secrets by definition.
+        let secret = secret || is_redacted(&field_type);
         let field_name = field.ident.clone();
         let span = field.span();
 
@@ -74,7 +235,15 @@ impl TryFrom<&syn::Field> for Field {
             field_name,
             field_type,
             optional,
+            secret,
             skip,
+            json,
+            allow_empty,
+            delimiter,
+            group,
+            requires,
+            aliases,
+            deprecated,
             infallible,
             desc,
             _attrs: field
@@ -103,7 +272,52 @@ impl Field {
         quote! { <#field_type as #field_trait> }
     }
 
+    pub(crate) const fn is_json(&self) -> bool {
+        self.json
+    }
+
+    pub(crate) const fn is_delimited(&self) -> bool {
+        self.delimiter.is_some()
+    }
+
+    pub(crate) const fn is_deprecated(&self) -> bool {
+        self.deprecated.is_some()
+    }
+
+    /// The mutually exclusive group of the field and its env var, if any.
+    pub(crate) fn group(&self) -> Option<(String, LitStr)> {
+        let group = self.group.as_ref()?.value();
+        Some((group, self.env_var.clone()?))
+    }
+
+    /// Produces a check that the env vars required by the field are set
+    /// whenever its own env var is set, if it has any requirements.
+    pub(crate) fn expand_requires_check(&self) -> Option<TokenStream> {
+        let env_var = self.env_var.as_ref()?;
+        if self.requires.is_empty() {
+            return None;
+        }
+        let requires = &self.requires;
+
+        Some(quote! {
+            if ::std::env::var_os(__prefixed(#env_var)).is_some() {
+                #(
+                    if ::std::env::var_os(__prefixed(#requires)).is_none() {
+                        __errors.push(FromEnvErr::missing_dependency(
+                            &__prefixed(#env_var),
+                            &__prefixed(#requires),
+                        ));
+                    }
+                )*
+            }
+        })
+    }
+
     pub(crate) fn assoc_err(&self) -> TokenStream {
+        if self.json {
+            return quote! { JsonParseError };
+        }
+
         let as_trait = self.as_trait();
 
         quote! { #as_trait::Error }
@@ -135,6 +349,36 @@ impl Field {
         Some(quote! { #n })
     }
 
+    /// Produces the error type carried by the variant for the field, if the
+    /// field has a variant and the type mentions any of the generic params.
+    pub(crate) fn generic_assoc_err(&self, params: &[Ident]) -> Option<TokenStream> {
+        self.enum_variant_name(0)?;
+        let assoc_err = self.assoc_err();
+        mentions_any(assoc_err.clone(), params).then_some(assoc_err)
+    }
+
+    /// Produces the bounds on the field type for the `where` clause of the
+    /// `FromEnv` impl, if the type mentions any of the generic params.
+    ///
+    /// Json and delimited fields are not bounded, as their bounds are not on
+    /// the field type itself. They must be bounded on the struct instead.
+    pub(crate) fn expand_where_predicates(&self, params: &[Ident]) -> Vec<TokenStream> {
+        let field_type = &self.field_type;
+        if !mentions_any(quote! { #field_type }, params) {
+            return vec![];
+        }
+
+        let mut predicates = vec![];
+        if self.skip || self.allow_empty {
+            predicates.push(quote! { #field_type: ::core::default::Default });
+        }
+        if !self.skip && !self.json && !self.is_delimited() {
+            let field_trait = self.trait_name();
+            predicates.push(quote! { #field_type: #field_trait });
+        }
+        predicates
+    }
+
     /// Produces the variant, containing the error type
     pub(crate) fn expand_enum_variant(&self, idx: usize) -> Option<TokenStream> {
         let variant_name = self.enum_variant_name(idx)?;
@@ -160,18 +404,9 @@ impl Field {
             return quote! {};
         }
 
-        let description = self.desc.clone().unwrap_or_default();
-        let optional = self.optional;
-
-        if let Some(env_var) = &self.env_var {
-            let var_name = env_var.value();
-
+        if let Some(item) = self.env_item_info() {
             return quote! {
-                items.push(&EnvItemInfo {
-                    var: #var_name,
-                    description: #description,
-                    optional: #optional,
-                });
+                items.push(#item);
             };
         }
 
@@ -183,6 +418,57 @@ impl Field {
         }
     }
 
+    /// Produces the `&EnvItemInfo` of the field, if it has an env var.
+    fn env_item_info(&self) -> Option<TokenStream> {
+        let var_name = self.env_var.as_ref()?.value();
+        let description = self.desc.clone().unwrap_or_default();
+        let optional = self.optional;
+        let secret = self.secret;
+        let aliases = &self.aliases;
+
+        Some(quote! {
+            const {
+                &EnvItemInfo::new(#var_name, #description, #optional)
+                    .with_secret(#secret)
+                    .with_aliases(&[#(#aliases),*])
+            }
+        })
+    }
+
+    /// Produces the check of the field for the `check_inventory` function,
+    /// pushing the field's env var to `missing` if it is required and not
+    /// set, or extending `missing` with the missing env vars of a nested
+    /// [`FromEnv`] field. Nested fields perform their own check, so that e.g.
+    /// an absent optional nested config is not reported.
+    pub(crate) fn expand_inventory_check(&self) -> Option<TokenStream> {
+        if self.skip {
+            return None;
+        }
+
+        if let Some(item) = self.env_item_info() {
+            if self.optional {
+                return None;
+            }
+            let env_var = self.env_var.as_ref()?;
+            let aliases = &self.aliases;
+            return Some(quote! {
+                if [#env_var, #(#aliases),*]
+                    .into_iter()
+                    .all(|var| ::std::env::var(var).is_err())
+                {
+                    missing.push(#item);
+                }
+            });
+        }
+
+        let field_ty = &self.field_type;
+        Some(quote! {
+            if let ::std::result::Result::Err(nested) = <#field_ty as FromEnv>::check_inventory() {
+                missing.extend(nested);
+            }
+        })
+    }
+
     pub(crate) fn expand_variant_display(&self, idx: usize) -> Option<TokenStream> {
         let variant_name = self.enum_variant_name(idx)?;
 
@@ -200,19 +486,44 @@ impl Field {
     }
 
     pub(crate) fn expand_item_from_env(&self, err_ident: &Ident, idx: usize) -> TokenStream {
-        // Produces code fo the following form:
+        // Produces code fo the following form, where `...` is the load
+        // result and failed loads push their errors into `__errors`:
         // ```rust
-        // // EITHER
-        // let field_name = env::var(#self.env_var.unwrap()).map_err(|e| e.map(#ErroEnum::FieldName))?;
+        // let field_name = match ... {
+        //     Ok(v) => Some(v),
+        //     Err(errs) => {
+        //         __errors.extend(errs.into_iter().map(|e| e.map(#ErroEnum::FieldName)));
+        //         None
+        //     }
+        // };
+        //
+        // // where the load result is EITHER
+        // FromEnvVar::from_env_var(&__prefixed(#self.env_var.unwrap())).map_err(|e| vec![e])
 
         // // OR
-        // let field_name =  FromEnvVar::from_env_var(#self.env_var.unwrap()).map_err(|e| e.map(#ErroEnum::FieldName))?;
+        // parse_json_env(&__prefixed(#self.env_var.unwrap())).map_err(|e| vec![e])
 
         // // OR
-        // let field_name =  FromEnv::from_env().map_err()?;
+        // from_env_var_delimited(&__prefixed(#self.env_var.unwrap()), #self.delimiter.unwrap()).map_err(|e| vec![e])
+
+        // // OR, for allow_empty fields, either of the above with
+        // match ... {
+        //     Err(FromEnvErr::Empty(_)) => Ok(Default::default()),
+        //     res => res,
+        // }.map_err(|e| vec![e])
+
+        // // OR, for aliased or deprecated fields, either of the above with
+        // let field_name = {
+        //     let __var = /* the first of the var and its aliases that is set */;
+        //     /* warn once if __var is deprecated */
+        //     ...(__var)...
+        // };
 
         // // OR
-        // let field_name = Default::default();
+        // FromEnv::from_env_all_prefixed(prefix)
+
+        // // OR, for skipped fields
+        // let field_name = Some(Default::default());
 
         //```
         let variant = self.enum_variant_name(idx);
@@ -220,14 +531,46 @@ impl Field {
 
         if self.skip {
             return quote! {
-                let #field_name = Default::default();
+                let #field_name = ::std::option::Option::Some(::std::default::Default::default());
             };
         }
 
-        let fn_invoc = if let Some(ref env_var) = self.env_var {
-            quote! { FromEnvVar::from_env_var(#env_var) }
+        // Aliased and deprecated fields pick the var to read at runtime.
+        let var_lookup = self.expand_var_lookup();
+        let read_var = match (&var_lookup, &self.env_var) {
+            (Some(_), _) => Some(quote! { &__var }),
+            (None, Some(env_var)) => Some(quote! { &__prefixed(#env_var) }),
+            (None, None) => None,
+        };
+
+        let fn_invoc = if let Some(ref env_var) = read_var {
+            if self.json {
+                quote! { parse_json_env(#env_var) }
+            } else if let Some(ref delimiter) = self.delimiter {
+                quote! { from_env_var_delimited(#env_var, #delimiter) }
+            } else {
+                quote! { FromEnvVar::from_env_var(#env_var) }
+            }
+        } else {
+            quote! { FromEnv::from_env_all_prefixed(prefix) }
+        };
+
+        let fn_invoc = if self.allow_empty {
+            quote! {
+                match #fn_invoc {
+                    Err(FromEnvErr::Empty(_)) => Ok(Default::default()),
+                    res => res,
+                }
+            }
         } else {
-            quote! { FromEnv::from_env() }
+            fn_invoc
+        };
+
+        // Nested configs already report every error.
+        let fn_invoc = if read_var.is_some() {
+            quote! { #fn_invoc.map_err(|e| ::std::vec![e]) }
+        } else {
+            fn_invoc
         };
 
         let map_line = if self.infallible {
@@ -236,9 +579,72 @@ impl Field {
             quote! { |e| e.map(#err_ident::#variant) }
         };
 
+        let fn_invoc = quote! {
+            match #fn_invoc {
+                ::std::result::Result::Ok(v) => ::std::option::Option::Some(v),
+                ::std::result::Result::Err(errs) => {
+                    __errors.extend(errs.into_iter().map(#map_line));
+                    ::std::option::Option::None
+                }
+            }
+        };
+
+        if let Some(var_lookup) = var_lookup {
+            return quote! {
+                let #field_name = {
+                    #var_lookup
+                    #fn_invoc
+                };
+            };
+        }
+
         quote! {
-            let #field_name = #fn_invoc
-                .map_err(#map_line)?;
+            let #field_name = #fn_invoc;
         }
     }
+
+    /// Produces the binding of `__var`, the env var to read, for aliased or
+    /// deprecated fields, followed by the deprecation warning, if any.
+    ///
+    /// The var is the first of the field's var and its aliases that is set,
+    /// or the field's var if none is. With aliases, the field's var is the
+    /// new name, and reading an alias is deprecated. Without, reading the
+    /// field's var is.
+    fn expand_var_lookup(&self) -> Option<TokenStream> {
+        let env_var = self.env_var.as_ref()?;
+        if self.aliases.is_empty() && self.deprecated.is_none() {
+            return None;
+        }
+        let aliases = &self.aliases;
+
+        let warning = self.deprecated.as_ref().map(|message| {
+            let is_deprecated = if aliases.is_empty() {
+                quote! { ::std::env::var_os(&__var).is_some() }
+            } else {
+                quote! { __var != __prefixed(#env_var) }
+            };
+            quote! {
+                if #is_deprecated {
+                    static WARNED: ::std::sync::Once = ::std::sync::Once::new();
+                    WARNED.call_once(|| {
+                        __tracing::warn!(
+                            var = __var.as_str(),
+                            "environment variable {} is deprecated: {}",
+                            __var,
+                            #message,
+                        );
+                    });
+                }
+            }
+        });
+
+        Some(quote! {
+            let __var: ::std::string::String = [#env_var, #(#aliases),*]
+                .into_iter()
+                .map(__prefixed)
+                .find(|var| ::std::env::var_os(var).is_some())
+                .unwrap_or_else(|| __prefixed(#env_var));
+            #warning
+        })
+    }
 }
@@ -1,7 +1,7 @@
 use proc_macro::TokenStream as Ts;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, spanned::Spanned, DeriveInput};
 
 mod field;
 use field::Field;
@@ -24,6 +24,16 @@ pub fn derive(input: Ts) -> Ts {
         unreachable!()
     };
 
+    // `FromEnv` requires `'static`, so lifetimes could only ever be `'static`.
+    if let Some(lifetime) = input.generics.lifetimes().next() {
+        return syn::Error::new(
+            lifetime.span(),
+            "FromEnv cannot be derived for structs with lifetime parameters",
+        )
+        .to_compile_error()
+        .into();
+    }
+
     let crate_name = input
         .attrs
         .iter()
@@ -56,6 +66,7 @@ pub fn derive(input: Ts) -> Ts {
 
     let input = Input {
         ident: input.ident.clone(),
+        generics: input.generics.clone(),
         fields,
         crate_name,
         tuple_like,
@@ -67,6 +78,8 @@ pub fn derive(input: Ts) -> Ts {
 struct Input {
     ident: syn::Ident,
 
+    generics: syn::Generics,
+
     fields: Vec<Field>,
 
     crate_name: syn::Path,
@@ -75,6 +88,61 @@ struct Input {
 }
 
 impl Input {
+    fn type_params(&self) -> Vec<syn::Ident> {
+        self.generics
+            .type_params()
+            .map(|param| param.ident.clone())
+            .collect()
+    }
+
+    /// Produces the `where` clause of the struct, extended with the given
+    /// predicates.
+    fn where_clause(&self, predicates: Vec<TokenStream>) -> TokenStream {
+        let mut where_clause =
+            self.generics
+                .where_clause
+                .clone()
+                .unwrap_or_else(|| syn::WhereClause {
+                    where_token: Default::default(),
+                    predicates: Default::default(),
+                });
+        where_clause
+            .predicates
+            .extend(predicates.into_iter().map(|predicate| {
+                syn::parse2::<syn::WherePredicate>(predicate).expect("valid predicate")
+            }));
+
+        if where_clause.predicates.is_empty() {
+            return quote! {};
+        }
+        quote! { #where_clause }
+    }
+
+    /// Produces the bounds on the field types that mention the generic
+    /// params.
+    fn field_predicates(&self) -> Vec<TokenStream> {
+        let params = self.type_params();
+        self.fields
+            .iter()
+            .flat_map(|field| field.expand_where_predicates(&params))
+            .collect()
+    }
+
+    /// Produces the `where` clause of the error type, or of an impl involving
+    /// it, bounding the field types and the error types that mention the
+    /// generic params.
+    fn error_where_clause(&self, bound: TokenStream) -> TokenStream {
+        let params = self.type_params();
+        let mut predicates = self.field_predicates();
+        predicates.extend(
+            self.fields
+                .iter()
+                .filter_map(|field| field.generic_assoc_err(&params))
+                .map(|assoc_err| quote! { #assoc_err: #bound }),
+        );
+        self.where_clause(predicates)
+    }
+
     fn field_names(&self) -> Vec<syn::Ident> {
         self.fields
             .iter()
@@ -135,6 +203,49 @@ impl Input {
             .collect::<Vec<_>>()
     }
 
+    /// Produces a check for each group of mutually exclusive env vars,
+    /// returning a conflict error if more than one of the group is set.
+    fn group_checks(&self) -> Vec<TokenStream> {
+        let mut groups = std::collections::BTreeMap::<_, Vec<_>>::new();
+        for (group, var) in self.fields.iter().filter_map(Field::group) {
+            groups.entry(group).or_default().push(var);
+        }
+
+        groups
+            .into_values()
+            .map(|vars| {
+                quote! {
+                    let set = [#(#vars),*]
+                        .into_iter()
+                        .map(__prefixed)
+                        .filter(|var| ::std::env::var_os(var).is_some())
+                        .collect::<::std::vec::Vec<_>>();
+                    if set.len() > 1 {
+                        let set = set
+                            .iter()
+                            .map(::std::string::String::as_str)
+                            .collect::<::std::vec::Vec<_>>();
+                        __errors.push(FromEnvErr::conflict(&set));
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn inventory_checks(&self) -> Vec<TokenStream> {
+        self.fields
+            .iter()
+            .filter_map(Field::expand_inventory_check)
+            .collect()
+    }
+
+    fn requires_checks(&self) -> Vec<TokenStream> {
+        self.fields
+            .iter()
+            .filter_map(Field::expand_requires_check)
+            .collect()
+    }
+
     fn item_from_envs(&self) -> Vec<TokenStream> {
         let error_ident = self.error_ident();
         self.fields
@@ -144,27 +255,76 @@ impl Input {
             .collect()
     }
 
+    fn variant_names(&self) -> Vec<TokenStream> {
+        self.fields
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, field)| field.enum_variant_name(idx))
+            .collect()
+    }
+
     fn expand_error(&self) -> TokenStream {
         let error_ident = self.error_ident();
         let struct_name_str = &self.ident.to_string();
 
-        let error_variants = self.error_variants();
-        let error_variant_displays = self.error_variant_displays();
-        let error_variant_sources = self.expand_variant_sources();
+        let mut error_variants = self.error_variants();
+        let mut error_variant_displays = self.error_variant_displays();
+        let mut error_variant_sources = self.expand_variant_sources();
+
+        let generics = &self.generics;
+        let (impl_generics, ty_generics, _) = self.generics.split_for_impl();
+        let enum_where = self.where_clause(self.field_predicates());
+        let display_where = self.error_where_clause(quote! { ::core::fmt::Display });
+        let error_where = self.error_where_clause(quote! { ::core::error::Error + 'static });
+
+        let params = self.type_params();
+        let derives = if params.is_empty() {
+            quote! {
+                #[derive(Debug, PartialEq, Eq, Clone)]
+            }
+        } else {
+            quote! {}
+        };
+        let generic_impls = if params.is_empty() {
+            quote! {}
+        } else {
+            // The params are only used via the error types of the fields,
+            // which does not count as a use.
+            error_variants.push(quote! {
+                #[doc(hidden)]
+                __Marker(
+                    ::core::convert::Infallible,
+                    ::core::marker::PhantomData<fn() -> (#(#params,)*)>,
+                )
+            });
+            error_variant_displays.push(quote! { Self::__Marker(never, _) => match *never {} });
+            error_variant_sources.push(quote! { Self::__Marker(never, _) => match *never {} });
+
+            // Implemented by hand, as derives would bound the params rather
+            // than the error types.
+            self.expand_generic_error_impls()
+        };
+
+        // An empty enum can only be matched by value.
+        let scrutinee = if error_variants.is_empty() {
+            quote! { *self }
+        } else {
+            quote! { self }
+        };
 
         quote! {
             #[doc = "Generated error type for [`FromEnv`] for"]
             #[doc = #struct_name_str]
             #[doc = ". This error type is used to represent errors that occur when trying to create an instance of the struct from environment variables."]
-            #[derive(Debug, PartialEq, Eq, Clone)]
-            pub enum #error_ident {
+            #derives
+            pub enum #error_ident #generics #enum_where {
                 #(#error_variants),*
             }
 
             #[automatically_derived]
-            impl ::core::fmt::Display for #error_ident {
+            impl #impl_generics ::core::fmt::Display for #error_ident #ty_generics #display_where {
                 fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-                    match self {
+                    match #scrutinee {
                         #(
                             #error_variant_displays,
                         )*
@@ -173,15 +333,74 @@ impl Input {
             }
 
             #[automatically_derived]
-            impl ::core::error::Error for #error_ident {
+            impl #impl_generics ::core::error::Error for #error_ident #ty_generics #error_where {
                 fn source(&self) -> Option<&(dyn ::core::error::Error + 'static)> {
-                    match self {
+                    match #scrutinee {
                         #(
                             #error_variant_sources,
                         )*
                     }
                 }
             }
+
+            #generic_impls
+        }
+    }
+
+    /// Produces the `Debug`, `Clone`, `PartialEq` and `Eq` impls of the error
+    /// type of a generic struct.
+    fn expand_generic_error_impls(&self) -> TokenStream {
+        let error_ident = self.error_ident();
+        let (impl_generics, ty_generics, _) = self.generics.split_for_impl();
+        let debug_where = self.error_where_clause(quote! { ::core::fmt::Debug });
+        let clone_where = self.error_where_clause(quote! { ::core::clone::Clone });
+        let eq_where = self.error_where_clause(quote! { ::core::cmp::PartialEq });
+        let total_eq_where = self.error_where_clause(quote! { ::core::cmp::Eq });
+
+        let variant_names = self.variant_names();
+        let variant_strs = variant_names.iter().map(ToString::to_string);
+
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::fmt::Debug for #error_ident #ty_generics #debug_where {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    match self {
+                        #(
+                            Self::#variant_names(err) => f.debug_tuple(#variant_strs).field(err).finish(),
+                        )*
+                        Self::__Marker(never, _) => match *never {},
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::core::clone::Clone for #error_ident #ty_generics #clone_where {
+                fn clone(&self) -> Self {
+                    match self {
+                        #(
+                            Self::#variant_names(err) => Self::#variant_names(err.clone()),
+                        )*
+                        Self::__Marker(never, _) => match *never {},
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::core::cmp::PartialEq for #error_ident #ty_generics #eq_where {
+                #[allow(unreachable_patterns)]
+                fn eq(&self, other: &Self) -> bool {
+                    match (self, other) {
+                        #(
+                            (Self::#variant_names(a), Self::#variant_names(b)) => a == b,
+                        )*
+                        (Self::__Marker(never, _), _) => match *never {},
+                        _ => false,
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::core::cmp::Eq for #error_ident #ty_generics #total_eq_where {}
         }
     }
 
@@ -194,16 +413,37 @@ impl Input {
 
     fn expand_impl(&self) -> TokenStream {
         let env_item_info = self.env_item_info();
+        let inventory_checks = self.inventory_checks();
         let struct_name = &self.ident;
         let error_ident = self.error_ident();
 
         let item_from_envs = self.item_from_envs();
+        let group_checks = self.group_checks();
+        let requires_checks = self.requires_checks();
         let struct_instantiation = self.instantiate_struct();
+        let field_names = self.field_names();
+
+        let (impl_generics, ty_generics, _) = self.generics.split_for_impl();
+        let impl_where = self
+            .error_where_clause(quote! { ::core::clone::Clone + ::core::error::Error + 'static });
+
+        // Statics are shared by all instantiations of a generic impl, so the
+        // inventory of a generic struct is left to the default, per-type
+        // cache.
+        let cached_inventory = self.generics.params.is_empty().then(|| {
+            quote! {
+                fn cached_inventory() -> &'static [&'static EnvItemInfo] {
+                    static INVENTORY: ::std::sync::OnceLock<::std::vec::Vec<&'static EnvItemInfo>> =
+                        ::std::sync::OnceLock::new();
+                    INVENTORY.get_or_init(<Self as FromEnv>::inventory)
+                }
+            }
+        });
 
         quote! {
             #[automatically_derived]
-            impl FromEnv for #struct_name {
-                type Error = #error_ident;
+            impl #impl_generics FromEnv for #struct_name #ty_generics #impl_where {
+                type Error = #error_ident #ty_generics;
 
                 fn inventory() -> ::std::vec::Vec<&'static EnvItemInfo> {
                     let mut items = ::std::vec::Vec::new();
@@ -213,14 +453,88 @@ impl Input {
                     items
                 }
 
+                #cached_inventory
+
+                fn check_inventory() -> ::std::result::Result<(), ::std::vec::Vec<&'static EnvItemInfo>> {
+                    let mut missing = ::std::vec::Vec::new();
+                    #(
+                        #inventory_checks
+                    )*
+                    if missing.is_empty() {
+                        ::std::result::Result::Ok(())
+                    } else {
+                        ::std::result::Result::Err(missing)
+                    }
+                }
+
                 fn from_env() -> ::std::result::Result<Self, FromEnvErr<Self::Error>> {
+                    <Self as FromEnv>::from_env_prefixed("")
+                }
+
+                fn from_env_prefixed(
+                    prefix: &str,
+                ) -> ::std::result::Result<Self, FromEnvErr<Self::Error>> {
+                    <Self as FromEnv>::from_env_all_prefixed(prefix)
+                        .map_err(|mut errs| errs.swap_remove(0))
+                }
+
+                fn from_env_all_prefixed(
+                    prefix: &str,
+                ) -> ::std::result::Result<Self, ::std::vec::Vec<FromEnvErr<Self::Error>>> {
+                    let __prefixed =
+                        |var: &str| -> ::std::string::String { ::std::format!("{prefix}{var}") };
+                    let mut __errors = ::std::vec::Vec::new();
+
                     #(
                         #item_from_envs
                     )*
 
+                    #(
+                        #group_checks
+                    )*
+
+                    #(
+                        #requires_checks
+                    )*
+
+                    #(
+                        let ::std::option::Option::Some(#field_names) = #field_names else {
+                            return ::std::result::Result::Err(__errors);
+                        };
+                    )*
+                    if !__errors.is_empty() {
+                        return ::std::result::Result::Err(__errors);
+                    }
+
                     ::std::result::Result::Ok(#struct_instantiation)
                 }
             }
+
+            #[automatically_derived]
+            impl #impl_generics #struct_name #ty_generics #impl_where {
+                /// Load from the environment with `prefix` prepended to the
+                /// names of the variables, as
+                /// [`FromEnv::from_env_all_prefixed`], additionally returning
+                /// the variables of the inventory, or their aliases, that were
+                /// set at load time. The names are returned without the
+                /// prefix. This records which configuration was actually
+                /// read, e.g. for an audit, regardless of later changes to the
+                /// environment.
+                pub fn from_env_with_present_vars(
+                    prefix: &str,
+                ) -> ::std::result::Result<
+                    (Self, ::std::vec::Vec<&'static str>),
+                    ::std::vec::Vec<FromEnvErr<<Self as FromEnv>::Error>>,
+                > {
+                    let present = <Self as FromEnv>::cached_inventory()
+                        .iter()
+                        .flat_map(|item| item.names())
+                        .filter(|var| ::std::env::var_os(::std::format!("{prefix}{var}")).is_some())
+                        .collect();
+                    let loaded = <Self as FromEnv>::from_env_all_prefixed(prefix)?;
+                    ::std::result::Result::Ok((loaded, present))
+                }
+            }
         }
     }
 
@@ -231,6 +545,30 @@ impl Input {
         let crate_name = &self.crate_name;
         let error_ident = self.error_ident();
 
+        let json_imports = if self.fields.iter().any(Field::is_json) {
+            quote! {
+                use #crate_name::utils::from_env::{parse_json_env, JsonParseError};
+            }
+        } else {
+            quote! {}
+        };
+
+        let delimited_imports = if self.fields.iter().any(Field::is_delimited) {
+            quote! {
+                use #crate_name::utils::from_env::from_env_var_delimited;
+            }
+        } else {
+            quote! {}
+        };
+
+        let tracing_imports = if self.fields.iter().any(Field::is_deprecated) {
+            quote! {
+                use #crate_name::deps::tracing as __tracing;
+            }
+        } else {
+            quote! {}
+        };
+
         let mod_ident =
             syn::parse_str::<syn::Ident>(&format!("__from_env_impls_{}", self.ident)).unwrap();
 
@@ -239,6 +577,9 @@ impl Input {
             mod #mod_ident {
                 use super::*;
                 use #crate_name::utils::from_env::{FromEnv, FromEnvErr, FromEnvVar, EnvItemInfo};
+                #json_imports
+                #delimited_imports
+                #tracing_imports
 
                 #expanded_impl
 
@@ -39,6 +39,238 @@ pub struct Nested {
     pub from_env_test: FromEnvTest,
 }
 
+#[derive(Debug, PartialEq, Eq, serde::Deserialize)]
+pub struct Limits {
+    pub rps: u32,
+    pub burst: u32,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct JsonCfg {
+    #[from_env(var = "LIMITS", desc = "Rate limits, as a JSON object", json)]
+    pub limits: Limits,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct DelimitedCfg {
+    #[from_env(
+        var = "DELIMITED_HEADERS",
+        desc = "Headers, separated by semicolons",
+        infallible,
+        delimiter = ";"
+    )]
+    pub headers: Vec<String>,
+
+    #[from_env(var = "DELIMITED_RETRIES", desc = "Retries")]
+    pub retries: u8,
+
+    #[from_env(
+        var = "DELIMITED_PORTS",
+        desc = "Ports, separated by semicolons",
+        delimiter = ";"
+    )]
+    pub ports: Vec<Port>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Port(pub u16);
+
+impl TryFrom<String> for Port {
+    type Error = std::num::ParseIntError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse().map(Self)
+    }
+}
+
+#[derive(Debug, FromEnv)]
+pub struct EmptyCfg {
+    #[from_env(var = "EMPTY_REQUIRED", desc = "A required string", infallible)]
+    pub required: String,
+
+    #[from_env(
+        var = "EMPTY_ALLOWED",
+        desc = "A string that may be empty",
+        infallible,
+        allow_empty
+    )]
+    pub allowed: String,
+
+    #[from_env(var = "EMPTY_COUNT", desc = "A count")]
+    pub count: u8,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct PresentCfg {
+    #[from_env(var = "PRESENT_PORT", desc = "A port")]
+    pub port: u16,
+
+    #[from_env(var = "PRESENT_HOST", desc = "A host", optional, infallible)]
+    pub host: Option<String>,
+
+    #[from_env(var = "PRESENT_NAME", desc = "A name", optional, infallible)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct GenericValue<T> {
+    #[from_env(var = "GENERIC_VALUE", desc = "A value")]
+    pub value: T,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct GenericCfg<T> {
+    #[from_env(var = "GENERIC_NAME", desc = "A name", infallible)]
+    pub name: String,
+
+    pub inner: T,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct DocCfg {
+    /// The port to
+    /// listen on.
+    #[from_env(var = "DOC_PORT")]
+    pub port: u16,
+
+    /// Overridden by the attribute.
+    #[from_env(var = "DOC_HOST", desc = "The host", infallible)]
+    pub host: String,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct GroupCfg {
+    #[from_env(
+        var = "GROUP_ENDPOINT",
+        desc = "An endpoint",
+        optional,
+        infallible,
+        group = "target"
+    )]
+    pub endpoint: Option<String>,
+
+    #[from_env(
+        var = "GROUP_SOCKET",
+        desc = "A socket",
+        optional,
+        infallible,
+        group = "target"
+    )]
+    pub socket: Option<String>,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct TlsCfg {
+    #[from_env(
+        var = "TLS_CERT",
+        desc = "Path to the certificate",
+        optional,
+        infallible,
+        requires = "TLS_KEY"
+    )]
+    pub cert: Option<String>,
+
+    #[from_env(
+        var = "TLS_KEY",
+        desc = "Path to the key",
+        optional,
+        infallible,
+        requires = "TLS_CERT"
+    )]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct RenamedCfg {
+    #[from_env(
+        var = "RENAMED_PORT",
+        desc = "The port",
+        alias = "OLD_PORT",
+        deprecated = "use RENAMED_PORT instead"
+    )]
+    pub port: u16,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct RenamedHostCfg {
+    #[from_env(
+        var = "RENAMED_HOST",
+        desc = "The host",
+        alias = "OLD_HOST",
+        infallible
+    )]
+    pub host: String,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct AuthCfg {
+    #[from_env(var = "AUTH_USER", desc = "The user", infallible)]
+    pub user: String,
+
+    #[from_env(var = "AUTH_PASS", desc = "The password", infallible)]
+    pub pass: String,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct OptionalNested {
+    #[from_env(var = "OPTIONAL_NESTED_URL", desc = "The url", infallible)]
+    pub url: String,
+
+    pub auth: Option<AuthCfg>,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct SecretCfg {
+    #[from_env(var = "SECRET_USER", desc = "The user", infallible)]
+    pub user: String,
+
+    #[from_env(var = "SECRET_PASS", desc = "The password", infallible, secret)]
+    pub pass: String,
+
+    #[from_env(var = "SECRET_TOKEN", desc = "The token", infallible)]
+    pub token: init4_bin_base::utils::from_env::Redacted,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct AnyhowCfg {
+    #[from_env(var = "ANYHOW_THREADS", desc = "Worker threads")]
+    pub threads: u8,
+}
+
+#[derive(Debug, FromEnv)]
+pub struct ValidateCfg {
+    #[from_env(var = "VALIDATE_PORT", desc = "The port")]
+    pub port: u16,
+
+    #[from_env(var = "VALIDATE_HOST", desc = "The host", infallible)]
+    pub host: String,
+}
+
+/// A hand-written config reading fixed variable names, so that it cannot be
+/// loaded with a prefix.
+#[derive(Debug)]
+pub struct FixedCfg;
+
+impl init4_bin_base::utils::from_env::FromEnv for FixedCfg {
+    type Error = std::convert::Infallible;
+
+    fn inventory() -> Vec<&'static init4_bin_base::utils::from_env::EnvItemInfo> {
+        Vec::new()
+    }
+
+    fn from_env() -> Result<Self, init4_bin_base::utils::from_env::FromEnvErr<Self::Error>> {
+        Ok(Self)
+    }
+}
+
+#[derive(Debug, FromEnv)]
+pub struct WithFixedCfg {
+    #[from_env(var = "FIXED_NAME", desc = "A name", infallible)]
+    pub name: String,
+
+    pub fixed: FixedCfg,
+}
+
 impl FromEnvTest {
     /// Get the memoized value
     pub fn get_memo(&self) -> &str {
@@ -49,7 +281,9 @@ impl FromEnvTest {
 #[cfg(test)]
 mod test {
     use super::*;
-    use init4_bin_base::utils::from_env::{EnvItemInfo, FromEnv};
+    use init4_bin_base::utils::from_env::{
+        effective_vars, EnvItemInfo, FromEnv, FromEnvErr, TupleEnvError,
+    };
 
     #[test]
     fn load_nested() {
@@ -80,11 +314,472 @@ mod test {
         assert_eq!(nested.ffffff, "5");
     }
 
+    #[test]
+    fn load_json() {
+        unsafe {
+            std::env::set_var("LIMITS", r#"{"rps":100,"burst":10}"#);
+        }
+
+        let cfg = JsonCfg::from_env().unwrap();
+        assert_eq!(
+            cfg.limits,
+            Limits {
+                rps: 100,
+                burst: 10
+            }
+        );
+
+        unsafe {
+            std::env::set_var("LIMITS", "not json");
+        }
+        assert!(matches!(
+            JsonCfg::from_env(),
+            Err(FromEnvErr::ParseError(JsonCfgEnvError::Limits(_)))
+        ));
+    }
+
+    #[test]
+    fn load_delimited() {
+        unsafe {
+            std::env::set_var("DELIMITED_HEADERS", "accept=a,b;x-id=1");
+            std::env::set_var("DELIMITED_RETRIES", "3");
+            std::env::set_var("DELIMITED_PORTS", "80;443");
+        }
+
+        let cfg = DelimitedCfg::from_env().unwrap();
+        assert_eq!(cfg.headers, vec!["accept=a,b", "x-id=1"]);
+        assert_eq!(cfg.retries, 3);
+        assert_eq!(cfg.ports, [Port(80), Port(443)]);
+
+        unsafe { std::env::set_var("DELIMITED_PORTS", "80;https") };
+        assert!(matches!(
+            DelimitedCfg::from_env(),
+            Err(FromEnvErr::ParseError(DelimitedCfgEnvError::Ports(_)))
+        ));
+    }
+
+    #[test]
+    fn load_empty() {
+        unsafe {
+            std::env::set_var("EMPTY_REQUIRED", "");
+            std::env::set_var("EMPTY_ALLOWED", "");
+            std::env::set_var("EMPTY_COUNT", "1");
+        }
+        assert!(matches!(
+            EmptyCfg::from_env(),
+            Err(FromEnvErr::Empty(var)) if var == "EMPTY_REQUIRED"
+        ));
+
+        unsafe {
+            std::env::set_var("EMPTY_REQUIRED", "present");
+        }
+        let cfg = EmptyCfg::from_env().unwrap();
+        assert_eq!(cfg.required, "present");
+        assert_eq!(cfg.allowed, "");
+
+        unsafe {
+            std::env::remove_var("EMPTY_ALLOWED");
+        }
+        assert!(matches!(
+            EmptyCfg::from_env(),
+            Err(FromEnvErr::EnvError(var, _)) if var == "EMPTY_ALLOWED"
+        ));
+    }
+
+    #[test]
+    fn present_vars() {
+        unsafe {
+            std::env::set_var("PRESENT_PORT", "8080");
+            std::env::set_var("PRESENT_NAME", "node");
+            std::env::remove_var("PRESENT_HOST");
+        }
+        let (cfg, present) = PresentCfg::from_env_with_present_vars("").unwrap();
+        assert_eq!(cfg.host, None);
+        assert_eq!(present, ["PRESENT_PORT", "PRESENT_NAME"]);
+
+        // The vars are those set at load time, not those set since.
+        unsafe {
+            std::env::set_var("PRESENT_HOST", "localhost");
+            std::env::remove_var("PRESENT_NAME");
+        }
+        assert_eq!(present, ["PRESENT_PORT", "PRESENT_NAME"]);
+
+        // Each prefix has its own vars.
+        unsafe {
+            std::env::set_var("TENANT_P_PRESENT_PORT", "9090");
+            std::env::set_var("TENANT_P_PRESENT_HOST", "localhost");
+        }
+        let (cfg, present) = PresentCfg::from_env_with_present_vars("TENANT_P_").unwrap();
+        assert_eq!(cfg.port, 9090);
+        assert_eq!(present, ["PRESENT_PORT", "PRESENT_HOST"]);
+
+        // Nothing is recorded if loading fails.
+        unsafe { std::env::set_var("TENANT_P_PRESENT_PORT", "not-a-port") };
+        assert!(PresentCfg::from_env_with_present_vars("TENANT_P_").is_err());
+    }
+
+    #[test]
+    fn load_generic() {
+        unsafe {
+            std::env::set_var("GENERIC_NAME", "node");
+            std::env::set_var("GENERIC_VALUE", "8080");
+        }
+        let (cfg, present) =
+            GenericCfg::<GenericValue<u16>>::from_env_with_present_vars("").unwrap();
+        assert_eq!(cfg.name, "node");
+        assert_eq!(cfg.inner.value, 8080);
+        assert_eq!(present, ["GENERIC_NAME", "GENERIC_VALUE"]);
+
+        let cfg = GenericCfg::<GenericValue<String>>::from_env().unwrap();
+        assert_eq!(cfg.inner.value, "8080");
+
+        let inventory = GenericCfg::<GenericValue<u8>>::inventory();
+        assert_eq!(inventory.len(), 2);
+        assert_eq!(inventory[1].var, "GENERIC_VALUE");
+
+        let err = GenericCfg::<GenericValue<u8>>::from_env().unwrap_err();
+        assert!(matches!(
+            err,
+            FromEnvErr::ParseError(GenericCfgEnvError::Inner(GenericValueEnvError::Value(_)))
+        ));
+        assert_eq!(err.clone(), err);
+    }
+
+    #[test]
+    fn doc_descriptions() {
+        let inventory = DocCfg::inventory();
+        assert_contains(
+            &inventory,
+            &EnvItemInfo::new("DOC_PORT", "The port to listen on.", false),
+        );
+        assert_contains(&inventory, &EnvItemInfo::new("DOC_HOST", "The host", false));
+    }
+
+    #[test]
+    fn optional_nested() {
+        unsafe {
+            std::env::set_var("OPTIONAL_NESTED_URL", "http://localhost");
+            std::env::remove_var("AUTH_USER");
+            std::env::remove_var("AUTH_PASS");
+        }
+        assert!(OptionalNested::from_env().unwrap().auth.is_none());
+        assert!(OptionalNested::check_inventory().is_ok());
+
+        unsafe { std::env::set_var("AUTH_USER", "alice") };
+        assert!(matches!(
+            OptionalNested::from_env(),
+            Err(FromEnvErr::EnvError(var, _)) if var == "AUTH_PASS"
+        ));
+        let missing = OptionalNested::check_inventory().unwrap_err();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].var, "AUTH_PASS");
+
+        unsafe { std::env::set_var("AUTH_PASS", "hunter2") };
+        let auth = OptionalNested::from_env().unwrap().auth.unwrap();
+        assert_eq!(auth.user, "alice");
+        assert_eq!(auth.pass, "hunter2");
+
+        unsafe {
+            std::env::remove_var("AUTH_USER");
+            std::env::remove_var("AUTH_PASS");
+        }
+    }
+
+    #[test]
+    fn secret_inventory() {
+        let secret = |var: &str| {
+            SecretCfg::inventory()
+                .into_iter()
+                .find(|item| item.var == var)
+                .unwrap()
+                .secret
+        };
+        assert!(!secret("SECRET_USER"));
+        assert!(secret("SECRET_PASS"));
+        assert!(secret("SECRET_TOKEN"));
+    }
+
+    #[test]
+    fn exclusive_group() {
+        unsafe {
+            std::env::set_var("GROUP_ENDPOINT", "http://localhost");
+            std::env::remove_var("GROUP_SOCKET");
+        }
+        let cfg = GroupCfg::from_env().unwrap();
+        assert_eq!(cfg.endpoint.as_deref(), Some("http://localhost"));
+        assert_eq!(cfg.socket, None);
+
+        unsafe {
+            std::env::set_var("GROUP_SOCKET", "/tmp/otlp.sock");
+        }
+        let err = GroupCfg::from_env().unwrap_err();
+        assert_eq!(
+            err,
+            FromEnvErr::conflict(&["GROUP_ENDPOINT", "GROUP_SOCKET"])
+        );
+        assert_eq!(
+            err.to_string(),
+            "environment variables GROUP_ENDPOINT, GROUP_SOCKET are mutually exclusive"
+        );
+    }
+
+    #[test]
+    fn required_together() {
+        unsafe {
+            std::env::remove_var("TLS_CERT");
+            std::env::remove_var("TLS_KEY");
+        }
+        let cfg = TlsCfg::from_env().unwrap();
+        assert_eq!(cfg.cert, None);
+        assert_eq!(cfg.key, None);
+
+        unsafe {
+            std::env::set_var("TLS_CERT", "/etc/tls/cert.pem");
+        }
+        let err = TlsCfg::from_env().unwrap_err();
+        assert_eq!(err, FromEnvErr::missing_dependency("TLS_CERT", "TLS_KEY"));
+        assert_eq!(
+            err.to_string(),
+            "environment variable TLS_KEY is required when TLS_CERT is set"
+        );
+
+        unsafe {
+            std::env::set_var("TLS_KEY", "/etc/tls/key.pem");
+        }
+        let cfg = TlsCfg::from_env().unwrap();
+        assert_eq!(cfg.key.as_deref(), Some("/etc/tls/key.pem"));
+    }
+
+    #[test]
+    fn deprecated_alias() {
+        use init4_bin_base::{deps::tracing, utils::capture::with_capture};
+
+        unsafe {
+            std::env::remove_var("RENAMED_PORT");
+            std::env::set_var("OLD_PORT", "8080");
+        }
+        let (subscriber, events) = with_capture();
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(RenamedCfg::from_env().unwrap().port, 8080);
+            assert_eq!(RenamedCfg::from_env().unwrap().port, 8080);
+
+            unsafe { std::env::set_var("RENAMED_PORT", "9090") };
+            assert_eq!(RenamedCfg::from_env().unwrap().port, 9090);
+        });
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, tracing::Level::WARN);
+        assert_eq!(events[0].field("var"), Some("OLD_PORT"));
+        assert_eq!(
+            events[0].message.as_deref(),
+            Some("environment variable OLD_PORT is deprecated: use RENAMED_PORT instead")
+        );
+    }
+
+    #[test]
+    fn optional_alias() {
+        unsafe {
+            std::env::remove_var("RENAMED_HOST");
+            std::env::remove_var("OLD_HOST");
+        }
+        assert!(<Option<RenamedHostCfg>>::from_env().unwrap().is_none());
+
+        unsafe { std::env::set_var("OLD_HOST", "localhost") };
+        let cfg = <Option<RenamedHostCfg>>::from_env().unwrap().unwrap();
+        assert_eq!(cfg.host, "localhost");
+        assert!(RenamedHostCfg::check_inventory().is_ok());
+        assert!(RenamedHostCfg::warn_unknown("OLD_HOST").is_empty());
+
+        let inventory = RenamedHostCfg::inventory();
+        assert_eq!(inventory[0].aliases, ["OLD_HOST"]);
+        let vars = effective_vars(&inventory);
+        assert_eq!(vars.get("OLD_HOST").map(String::as_str), Some("localhost"));
+
+        unsafe { std::env::remove_var("OLD_HOST") };
+    }
+
+    #[test]
+    fn load_prefixed() {
+        unsafe {
+            std::env::set_var("TENANT_A_FIELD1", "10");
+            std::env::set_var("TENANT_A_FIELD2", "20");
+            std::env::set_var("TENANT_A_FIELD3", "30");
+            std::env::remove_var("TENANT_A_FIELD4");
+            std::env::set_var("TENANT_A_FFFFFF", "50");
+        }
+
+        let nested = Nested::from_env_prefixed("TENANT_A_").unwrap();
+        assert_eq!(nested.from_env_test.tony, 10);
+        assert_eq!(nested.from_env_test.charles, 20);
+        assert_eq!(nested.from_env_test.patrick, "30");
+        assert_eq!(nested.from_env_test.oliver, None);
+        assert_eq!(nested.ffffff, "50");
+
+        unsafe {
+            std::env::set_var("TENANT_A_GROUP_ENDPOINT", "http://localhost");
+            std::env::set_var("TENANT_A_GROUP_SOCKET", "/tmp/otlp.sock");
+            std::env::set_var("TENANT_A_TLS_CERT", "/etc/tls/cert.pem");
+            std::env::remove_var("TENANT_A_TLS_KEY");
+            std::env::remove_var("TENANT_A_RENAMED_PORT");
+            std::env::set_var("TENANT_A_OLD_PORT", "8080");
+        }
+        assert_eq!(
+            GroupCfg::from_env_prefixed("TENANT_A_").unwrap_err(),
+            FromEnvErr::conflict(&["TENANT_A_GROUP_ENDPOINT", "TENANT_A_GROUP_SOCKET"])
+        );
+        assert_eq!(
+            TlsCfg::from_env_prefixed("TENANT_A_").unwrap_err(),
+            FromEnvErr::missing_dependency("TENANT_A_TLS_CERT", "TENANT_A_TLS_KEY")
+        );
+        assert_eq!(
+            RenamedCfg::from_env_prefixed("TENANT_A_").unwrap().port,
+            8080
+        );
+    }
+
+    #[test]
+    fn nested_prefix_unsupported() {
+        unsafe {
+            std::env::set_var("FIXED_NAME", "node");
+            std::env::set_var("TENANT_A_FIXED_NAME", "tenant");
+        }
+        assert_eq!(WithFixedCfg::from_env().unwrap().name, "node");
+        let err = WithFixedCfg::from_env_prefixed("TENANT_A_").unwrap_err();
+        assert!(matches!(
+            err,
+            FromEnvErr::PrefixUnsupported { ref config, ref prefix }
+                if config.ends_with("FixedCfg") && prefix == "TENANT_A_"
+        ));
+    }
+
+    #[test]
+    fn load_all() {
+        unsafe {
+            std::env::remove_var("ALL_FFFFFF");
+            std::env::set_var("ALL_FIELD1", "one");
+            std::env::remove_var("ALL_FIELD2");
+            std::env::set_var("ALL_FIELD3", "3");
+            std::env::set_var("ALL_TLS_CERT", "/etc/tls/cert.pem");
+            std::env::remove_var("ALL_TLS_KEY");
+        }
+
+        let errs = Nested::from_env_all_prefixed("ALL_").unwrap_err();
+        assert_eq!(errs.len(), 3);
+        assert_eq!(errs[0].var(), Some("ALL_FFFFFF"));
+        assert!(matches!(
+            errs[1],
+            FromEnvErr::ParseError(NestedEnvError::FromEnvTest(FromEnvTestEnvError::Tony(_)))
+        ));
+        assert_eq!(errs[2].var(), Some("ALL_FIELD2"));
+        assert_eq!(
+            Nested::from_env_prefixed("ALL_").unwrap_err(),
+            errs[0].clone()
+        );
+
+        let errs = TlsCfg::from_env_all_prefixed("ALL_").unwrap_err();
+        assert_eq!(
+            errs,
+            [FromEnvErr::missing_dependency(
+                "ALL_TLS_CERT",
+                "ALL_TLS_KEY"
+            )]
+        );
+
+        unsafe {
+            std::env::set_var("ALL_FFFFFF", "5");
+            std::env::set_var("ALL_FIELD1", "1");
+            std::env::set_var("ALL_FIELD2", "2");
+        }
+        assert_eq!(Nested::from_env_all_prefixed("ALL_").unwrap().ffffff, "5");
+    }
+
+    #[test]
+    fn load_tuple() {
+        type Cfgs = (AnyhowCfg, AuthCfg);
+
+        let vars: Vec<_> = Cfgs::inventory().iter().map(|item| item.var).collect();
+        assert_eq!(vars, ["ANYHOW_THREADS", "AUTH_USER", "AUTH_PASS"]);
+
+        unsafe {
+            std::env::set_var("TUPLE_ANYHOW_THREADS", "many");
+            std::env::set_var("TUPLE_AUTH_USER", "alice");
+            std::env::remove_var("TUPLE_AUTH_PASS");
+        }
+        assert!(matches!(
+            Cfgs::from_env_prefixed("TUPLE_"),
+            Err(FromEnvErr::ParseError(TupleEnvError::First(_)))
+        ));
+        let errs = Cfgs::from_env_all_prefixed("TUPLE_").unwrap_err();
+        assert_eq!(errs.len(), 2);
+        assert_eq!(errs[1].var(), Some("TUPLE_AUTH_PASS"));
+
+        unsafe {
+            std::env::set_var("TUPLE_ANYHOW_THREADS", "4");
+            std::env::set_var("TUPLE_AUTH_PASS", "hunter2");
+        }
+        let (threads, auth) = Cfgs::from_env_prefixed("TUPLE_").unwrap();
+        assert_eq!(threads.threads, 4);
+        assert_eq!(auth.pass, "hunter2");
+    }
+
+    #[test]
+    fn validate_env() {
+        unsafe {
+            std::env::set_var("VALIDATE_PORT", "eighty");
+            std::env::remove_var("VALIDATE_HOST");
+        }
+        let missing = ValidateCfg::check_inventory().unwrap_err();
+        assert_eq!(missing.len(), 1);
+
+        let errs = ValidateCfg::validate_env().unwrap_err();
+        assert_eq!(errs.len(), 2);
+        assert!(matches!(
+            errs[0],
+            FromEnvErr::ParseError(ValidateCfgEnvError::Port(_))
+        ));
+        assert_eq!(errs[1].var(), Some("VALIDATE_HOST"));
+
+        unsafe {
+            std::env::set_var("VALIDATE_PORT", "80");
+            std::env::set_var("VALIDATE_HOST", "localhost");
+        }
+        assert!(ValidateCfg::validate_env().is_ok());
+    }
+
+    #[test]
+    fn into_anyhow() {
+        fn assert_send_sync<T: std::error::Error + Send + Sync + 'static>() {}
+        assert_send_sync::<FromEnvErr<FromEnvTestEnvError>>();
+        assert_send_sync::<FromEnvErr<GenericCfgEnvError<GenericValue<u16>>>>();
+
+        fn load() -> anyhow::Result<u8> {
+            Ok(AnyhowCfg::from_env()?.threads)
+        }
+
+        unsafe { std::env::set_var("ANYHOW_THREADS", "many") };
+        let err = load().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            AnyhowCfg::from_env().unwrap_err().to_string()
+        );
+        assert!(err
+            .downcast_ref::<FromEnvErr<AnyhowCfgEnvError>>()
+            .is_some());
+        assert_eq!(
+            err.chain().last().unwrap().to_string(),
+            "invalid digit found in string"
+        );
+
+        unsafe { std::env::set_var("ANYHOW_THREADS", "4") };
+        assert_eq!(load().unwrap(), 4);
+    }
+
     fn assert_contains(vec: &Vec<&'static EnvItemInfo>, item: &EnvItemInfo) {
-        let item = vec.iter().find(|i| i.var == item.var).unwrap();
-        assert_eq!(item.var, item.var);
-        assert_eq!(item.description, item.description);
-        assert_eq!(item.optional, item.optional);
+        let found = vec.iter().find(|i| i.var == item.var).unwrap();
+        assert_eq!(found.var, item.var);
+        assert_eq!(found.description, item.description);
+        assert_eq!(found.optional, item.optional);
     }
 
     #[test]
@@ -93,35 +788,19 @@ mod test {
         assert_eq!(fet_inv.len(), 4);
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD1",
-                description: "Tony is cool and a u8",
-                optional: false,
-            },
+            &EnvItemInfo::new("FIELD1", "Tony is cool and a u8", false),
         );
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD2",
-                description: "Charles is a u64",
-                optional: false,
-            },
+            &EnvItemInfo::new("FIELD2", "Charles is a u64", false),
         );
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD3",
-                description: "Patrick is a String",
-                optional: false,
-            },
+            &EnvItemInfo::new("FIELD3", "Patrick is a String", false),
         );
         assert_contains(
             &fet_inv,
-            &EnvItemInfo {
-                var: "FIELD4",
-                description: "Oliver is an Option<String>",
-                optional: true,
-            },
+            &EnvItemInfo::new("FIELD4", "Oliver is an Option<String>", true),
         );
 
         let nest_inv = Nested::inventory();
@@ -131,11 +810,26 @@ mod test {
         }
         assert_contains(
             &nest_inv,
-            &EnvItemInfo {
-                var: "FFFFFF",
-                description: "This is a guy named ffffff",
-                optional: false,
-            },
+            &EnvItemInfo::new("FFFFFF", "This is a guy named ffffff", false),
         );
     }
+
+    #[test]
+    fn cached_inventory() {
+        let first = Nested::cached_inventory();
+        assert!(std::ptr::eq(first, Nested::cached_inventory()));
+        assert_eq!(first, Nested::inventory());
+
+        // Generic structs are cached per instantiation.
+        let u8s = GenericCfg::<GenericValue<u8>>::cached_inventory();
+        assert!(std::ptr::eq(
+            u8s,
+            GenericCfg::<GenericValue<u8>>::cached_inventory()
+        ));
+        assert!(!std::ptr::eq(
+            u8s,
+            GenericCfg::<GenericValue<u16>>::cached_inventory()
+        ));
+        assert_eq!(u8s, GenericCfg::<GenericValue<u8>>::inventory());
+    }
 }
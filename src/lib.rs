@@ -12,14 +12,51 @@
 #![deny(unused_must_use, rust_2018_idioms)]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+use utils::from_env::FromEnv;
+
 /// Signet utilities.
 pub mod utils {
     /// Prometheus metrics utilities.
     pub mod metrics;
 
     /// OpenTelemetry utilities.
+    #[cfg(feature = "otlp")]
     pub mod otlp;
 
+    /// Stand-in for the OpenTelemetry utilities when the `otlp` feature is
+    /// disabled.
+    #[cfg(not(feature = "otlp"))]
+    pub mod otlp {
+        /// Uninhabited stand-in for the OTLP guard. [`TracingGuard::otel`]
+        /// always returns [`None`] without the `otlp` feature.
+        ///
+        /// [`TracingGuard::otel`]: crate::utils::tracing::TracingGuard::otel
+        #[derive(Debug)]
+        #[allow(missing_copy_implementations)]
+        pub enum OtelGuard {}
+    }
+
+    /// HTTP server serving the prometheus metrics.
+    #[cfg(feature = "metrics-server")]
+    mod metrics_server;
+
+    /// Process and tokio runtime metrics.
+    pub mod process_metrics;
+
+    /// Recording metrics declared on tracing events.
+    pub mod event_metrics;
+
+    /// JSON rendering of recorded metrics.
+    #[cfg(feature = "json")]
+    pub mod metrics_json;
+
+    /// OpenTelemetry bridge for the [`metrics`] crate.
+    #[cfg(feature = "otlp")]
+    pub mod otlp_metrics;
+
+    /// Typed log filter directives.
+    pub mod filter;
+
     /// [`FromEnv`], [`FromEnvVar`] traits and related utilities.
     ///
     /// [`FromEnv`]: from_env::FromEnv
@@ -28,16 +65,34 @@ pub mod utils {
 
     /// Tracing utilities.
     pub mod tracing;
+
+    /// Tower middleware instrumenting HTTP requests.
+    #[cfg(feature = "tower")]
+    pub mod http;
+
+    /// Capturing events in tests.
+    #[cfg(feature = "test-util")]
+    pub mod capture;
+
+    /// Sentry error reporting.
+    #[cfg(feature = "sentry")]
+    mod sentry;
 }
 
 /// Re-exports of common dependencies.
 pub mod deps {
     pub use metrics;
+    #[cfg(feature = "otlp")]
     pub use opentelemetry;
+    #[cfg(feature = "otlp")]
     pub use opentelemetry_otlp;
+    #[cfg(feature = "otlp")]
     pub use opentelemetry_sdk;
+    #[cfg(feature = "tokio")]
+    pub use tokio;
     pub use tracing;
     pub use tracing_core;
+    #[cfg(feature = "otlp")]
     pub use tracing_opentelemetry;
     pub use tracing_subscriber;
 }
@@ -49,23 +104,34 @@ pub mod deps {
 /// - Determine whether to enable OTLP
 /// - Install a global tracing subscriber, using the OTLP provider if enabled
 /// - Read environment configuration for metrics
-/// - Install a global metrics recorder and serve it over HTTP on 0.0.0.0
+/// - Install a global metrics recorder and serve it over HTTP on 0.0.0.0,
+///   additionally exporting via OTLP if OTLP metrics are enabled, unless
+///   `TRACING_METRICS_ENABLED` is `false`
+/// - If `TRACING_WARN_UNKNOWN_VARS` is set, warn about each set `TRACING_`
+///   or `OTEL_` env var that is not read, e.g. a typo such as `OTEL_ENDPONT`
+/// - Log the OTLP and metrics configuration, see
+///   [`FromEnv::log_effective`]
 ///
 /// See [`init_tracing`] and [`init_metrics`] for more
 /// details on specific actions taken and env vars read.
 ///
 /// # Returns
 ///
-/// The OpenTelemetry guard, if OTLP is enabled. This guard should be kept alive
-/// for the lifetime of the program to ensure the exporter continues to send
-/// data to the remote API.
+/// The [`AppGuard`], holding the tracing guard and the metrics listener. This
+/// guard should be kept alive for the lifetime of the program to ensure the
+/// exporter continues to send data to the remote API, buffered log lines are
+/// flushed, and metrics are served.
+///
+/// # Repeated calls
+///
+/// The first caller wins. If tracing was already initialized by this crate,
+/// e.g. by another library in the same process, this does nothing, and the
+/// returned guard holds neither an OTEL provider nor a metrics listener.
 ///
 /// [`init_tracing`]: utils::tracing::init_tracing
 /// [`init_metrics`]: utils::metrics::init_metrics
-pub fn trace() -> Option<utils::otlp::OtelGuard> {
-    let guard = utils::tracing::init_tracing();
-    utils::metrics::init_metrics();
-    guard
+pub fn trace() -> AppGuard {
+    TracingBuilder::new().init()
 }
 
 /// Init metrics and tracing with journald, including OTLP if enabled.
@@ -75,24 +141,154 @@ pub fn trace() -> Option<utils::otlp::OtelGuard> {
 /// - Determine whether to enable OTLP
 /// - Install a global tracing subscriber, using the OTLP provider if enabled
 /// - Read environment configuration for metrics
-/// - Install a global metrics recorder and serve it over HTTP on 0.0.0.0
+/// - Install a global metrics recorder and serve it over HTTP on 0.0.0.0,
+///   additionally exporting via OTLP if OTLP metrics are enabled
 ///
 /// See [`init_tracing_with_journald`] and [`init_metrics`] for more
 /// details on specific actions taken and env vars read.
 ///
 /// # Returns
 ///
-/// The OpenTelemetry guard, if OTLP is enabled. This guard should be kept alive
-/// for the lifetime of the program to ensure the exporter continues to send
-/// data to the remote API.
+/// The [`AppGuard`], holding the tracing guard and the metrics listener. This
+/// guard should be kept alive for the lifetime of the program to ensure the
+/// exporter continues to send data to the remote API, buffered log lines are
+/// flushed, and metrics are served.
 ///
 /// [`init_tracing_with_journald`]: utils::tracing::init_tracing_with_journald
 /// [`init_metrics`]: utils::metrics::init_metrics
 #[cfg(feature = "journald")]
-pub fn trace_with_journald() -> Option<utils::otlp::OtelGuard> {
-    let guard = utils::tracing::init_tracing_with_journald();
-    utils::metrics::init_metrics();
-    guard
+pub fn trace_with_journald() -> AppGuard {
+    TracingBuilder {
+        overrides: utils::tracing::Overrides {
+            journald: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+    .init()
+}
+
+/// Init metrics and tracing as [`trace`], running the metrics on the given
+/// tokio runtime rather than the ambient one, if any.
+///
+/// With the `metrics-server` feature, the prometheus exporter is spawned on
+/// the runtime, rather than on a dedicated thread without an ambient one, and
+/// stops when the runtime shuts down. If `TRACING_PROCESS_METRICS` is set,
+/// the stats of this runtime are recorded. Nothing else is moved to the
+/// runtime: tracing, including the OTLP batch processors, runs as with
+/// [`trace`], on dedicated threads.
+///
+/// ```no_run
+/// let runtime = tokio::runtime::Builder::new_multi_thread()
+///     .worker_threads(1)
+///     .thread_name("telemetry")
+///     .enable_all()
+///     .build()
+///     .unwrap();
+/// let _guard = rust_tracing::trace_with_metrics_on_runtime(runtime.handle().clone());
+/// ```
+#[cfg(feature = "tokio")]
+pub fn trace_with_metrics_on_runtime(handle: tokio::runtime::Handle) -> AppGuard {
+    let _enter = handle.enter();
+    trace()
+}
+
+/// Guard returned by [`trace`], bundling the tracing guard and the metrics
+/// listener. It should be held for the lifetime of the `main` function.
+///
+/// When dropped, the tracing guard is dropped first, flushing and shutting
+/// down the OTEL provider and log file, so that spans recorded during
+/// shutdown are not lost. The metrics listener is stopped last.
+#[derive(Debug)]
+#[must_use = "dropping the guard shuts down tracing and metrics"]
+pub struct AppGuard {
+    tracing: utils::tracing::TracingGuard,
+    metrics: utils::metrics::MetricsGuard,
+}
+
+impl AppGuard {
+    /// Initialize metrics, forwarding them via OTLP if the tracing guard
+    /// holds an OTEL provider with metrics enabled, then warn about unknown
+    /// env vars if enabled.
+    fn init_metrics(
+        tracing: utils::tracing::TracingGuard,
+        cfg: utils::metrics::MetricsConfig,
+    ) -> Self {
+        let metrics = utils::metrics::init_metrics_with_config(
+            cfg,
+            #[cfg(feature = "otlp")]
+            tracing
+                .otel()
+                .and_then(utils::otlp::OtelGuard::metrics_recorder),
+        );
+        utils::tracing::warn_unknown_env_vars();
+        Self { tracing, metrics }
+    }
+
+    /// A guard holding nothing, if tracing was already initialized. See
+    /// [`trace`].
+    fn existing() -> Option<Self> {
+        utils::tracing::TracingGuard::existing().map(|tracing| {
+            Self {
+                tracing,
+                metrics: utils::metrics::MetricsGuard::empty(),
+            }
+        })
+    }
+
+    /// The tracing guard.
+    pub const fn tracing(&self) -> &utils::tracing::TracingGuard {
+        &self.tracing
+    }
+
+    /// The OTEL guard, if OTLP is enabled. See
+    /// [`TracingGuard::otel`](utils::tracing::TracingGuard::otel).
+    pub const fn otel(&self) -> Option<&utils::otlp::OtelGuard> {
+        self.tracing.otel()
+    }
+
+    /// Shut down as when dropped, but without blocking the async runtime
+    /// while the OTEL provider flushes. Prefer awaiting this at the end of an
+    /// async `main` over dropping the guard.
+    ///
+    /// See [`TracingGuard::shutdown`](utils::tracing::TracingGuard::shutdown).
+    pub async fn shutdown(self) {
+        self.tracing.shutdown().await;
+        drop(self.metrics);
+    }
+
+    /// Shut down as [`AppGuard::shutdown`] when the process receives `SIGINT`
+    /// or `SIGTERM`, then exit the process with the conventional status of
+    /// the signal, `130` or `143`. The OTEL provider is flushed, buffered log
+    /// lines are written, and the metrics listener is stopped before exiting.
+    ///
+    /// This installs process-global signal handlers, replacing the default
+    /// behavior of these signals for the whole process. The guard is moved
+    /// into a task spawned on the current runtime, and is dropped as usual
+    /// if the program exits otherwise.
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     rust_tracing::trace().install_shutdown_handler();
+    ///     // do stuff until killed
+    /// }
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if not called within a tokio runtime, or if
+    /// the signal handlers cannot be installed.
+    #[cfg(feature = "signal")]
+    pub fn install_shutdown_handler(self) {
+        utils::tracing::exit_on_signal(self.shutdown());
+    }
+
+    /// Split the guard into the tracing and metrics guards, e.g. to pass the
+    /// tracing guard to [`utils::tracing::reinit_tracing`].
+    pub fn into_parts(self) -> (utils::tracing::TracingGuard, utils::metrics::MetricsGuard) {
+        (self.tracing, self.metrics)
+    }
 }
 
 /// Init tracing only, including OTLP if enabled.
@@ -107,12 +303,136 @@ pub fn trace_with_journald() -> Option<utils::otlp::OtelGuard> {
 ///
 /// # Returns
 ///
-/// The OpenTelemetry guard, if OTLP is enabled. This guard should be kept alive
-/// for the lifetime of the program to ensure the exporter continues to send
-/// data to the remote API.
+/// The tracing guard, holding the OpenTelemetry provider if OTLP is enabled
+/// and the log file writer if file logging is enabled. This guard should be
+/// kept alive for the lifetime of the program to ensure the exporter continues
+/// to send data to the remote API, and buffered log lines are flushed.
 ///
 /// [`init_tracing`]: utils::tracing::init_tracing
 /// [`init_metrics`]: utils::metrics::init_metrics
-pub fn trace_only() -> Option<utils::otlp::OtelGuard> {
+pub fn trace_only() -> utils::tracing::TracingGuard {
     utils::tracing::init_tracing()
 }
+
+/// Programmatic configuration of metrics and tracing, as an alternative to
+/// env vars.
+///
+/// Settings that are not set via the builder fall back to the env vars read
+/// by [`trace`]. [`TracingBuilder::init`] then performs the same actions as
+/// [`trace`].
+///
+/// ```no_run
+/// # use rust_tracing::TracingBuilder;
+/// let guard = TracingBuilder::new()
+///     .metrics_port(9100)
+///     .log_json(true)
+///     .log_filter("info,my_crate=debug")
+///     .init();
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use = "builders do nothing unless initialized"]
+pub struct TracingBuilder {
+    overrides: utils::tracing::Overrides,
+    metrics: Option<utils::metrics::MetricsConfig>,
+    metrics_port: Option<u16>,
+}
+
+impl TracingBuilder {
+    /// Create a builder, with all settings taken from the environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the port on which to serve metrics, overriding
+    /// `TRACING_METRICS_PORT`, and the port of [`TracingBuilder::metrics`].
+    pub const fn metrics_port(mut self, port: u16) -> Self {
+        self.metrics_port = Some(port);
+        self
+    }
+
+    /// Set the metrics configuration, overriding all the `TRACING_METRICS_`
+    /// env vars, see [`MetricsConfig`].
+    ///
+    /// [`MetricsConfig`]: utils::metrics::MetricsConfig
+    pub fn metrics(mut self, cfg: utils::metrics::MetricsConfig) -> Self {
+        self.metrics = Some(cfg);
+        self
+    }
+
+    /// Set the log format, overriding `TRACING_LOG_FORMAT` and
+    /// `TRACING_LOG_JSON`.
+    pub const fn log_format(mut self, format: utils::tracing::LogFormat) -> Self {
+        self.overrides.format = Some(format);
+        self
+    }
+
+    /// Enable or disable JSON logging. Shorthand for
+    /// [`TracingBuilder::log_format`] with `json` or `full`.
+    pub const fn log_json(self, json: bool) -> Self {
+        self.log_format(if json {
+            utils::tracing::LogFormat::Json
+        } else {
+            utils::tracing::LogFormat::Full
+        })
+    }
+
+    /// Set the log filter directives, overriding `TRACING_FILTER` and
+    /// `RUST_LOG`. Invalid directives are reported, and the environment is
+    /// used instead.
+    pub fn log_filter(mut self, directives: impl Into<String>) -> Self {
+        self.overrides.filter = utils::filter::LogFilter::new(directives)
+            .inspect_err(|e| eprintln!("invalid log filter: {e}"))
+            .ok();
+        self
+    }
+
+    /// Set the OTLP endpoint, overriding `OTEL_EXPORTER_OTLP_ENDPOINT`. This
+    /// enables OTLP exporting. The remaining OTLP settings are read from the
+    /// environment.
+    #[cfg(feature = "otlp")]
+    pub fn otlp_endpoint(mut self, endpoint: url::Url) -> Self {
+        self.overrides.otlp_endpoint = Some(endpoint);
+        self
+    }
+
+    /// Set the minimum level to export via OTLP, overriding `OTEL_LEVEL`.
+    #[cfg(feature = "otlp")]
+    pub const fn otlp_level(mut self, level: tracing::Level) -> Self {
+        self.overrides.otlp_level = Some(level);
+        self
+    }
+
+    /// Set the name of the tracer, reported as the instrumentation scope of
+    /// exported spans, overriding `OTEL_TRACER_NAME`.
+    #[cfg(feature = "otlp")]
+    pub fn otlp_tracer_name(mut self, name: impl Into<String>) -> Self {
+        self.overrides.otlp_tracer_name = Some(name.into());
+        self
+    }
+
+    /// Init metrics and tracing, as [`trace`]. If tracing was already
+    /// initialized, the settings are ignored.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if a global subscriber or metrics recorder
+    /// has already been set other than by this crate, or if the metrics
+    /// exporter fails to install.
+    pub fn init(self) -> AppGuard {
+        // Held until metrics are installed too, so that concurrent callers
+        // do not install them twice.
+        let _lock = utils::tracing::init_lock();
+        if let Some(guard) = AppGuard::existing() {
+            return guard;
+        }
+        let tracing = utils::tracing::try_init_tracing_locked(self.overrides)
+            .expect("failed to install tracing subscriber");
+        let mut metrics = self
+            .metrics
+            .unwrap_or_else(|| utils::metrics::MetricsConfig::from_env().unwrap());
+        if let Some(port) = self.metrics_port {
+            metrics.port = port;
+        }
+        AppGuard::init_metrics(tracing, metrics)
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use tracing::{
+    Event,
+    Level,
+    Subscriber,
+    field::{
+        Field,
+        Visit,
+    },
+};
+use tracing_subscriber::{
+    Layer,
+    Registry,
+    layer::{
+        Context,
+        SubscriberExt,
+    },
+};
+
+/// Buffer of the events recorded by a [`CaptureLayer`].
+pub type CapturedEvents = Arc<Mutex<Vec<CapturedEvent>>>;
+
+/// An event recorded by a [`CaptureLayer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedEvent {
+    /// The level of the event.
+    pub level: Level,
+    /// The target of the event.
+    pub target: String,
+    /// The `message` field of the event, if any.
+    pub message: Option<String>,
+    /// The other fields of the event, in recording order, formatted with
+    /// their [`Debug`] impls. Strings are recorded unquoted.
+    pub fields: Vec<(String, String)>,
+}
+
+impl CapturedEvent {
+    /// Get the value of a field, other than the message, by name.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Visit for CapturedEvent {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_owned());
+        } else {
+            self.fields
+                .push((field.name().to_owned(), value.to_owned()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{value:?}"));
+        } else {
+            self.fields
+                .push((field.name().to_owned(), format!("{value:?}")));
+        }
+    }
+}
+
+/// A [`Layer`] that records every event it sees into a shared buffer, so that
+/// tests may assert on what was logged.
+///
+/// See [`with_capture`] for a ready-made subscriber.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureLayer {
+    events: CapturedEvents,
+}
+
+impl CaptureLayer {
+    /// Create a new layer, with an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the buffer the events are recorded into.
+    pub fn events(&self) -> CapturedEvents {
+        Arc::clone(&self.events)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut captured = CapturedEvent {
+            level: *meta.level(),
+            target: meta.target().to_owned(),
+            message: None,
+            fields: vec![],
+        };
+        event.record(&mut captured);
+        self.events.lock().unwrap().push(captured);
+    }
+}
+
+/// Create a subscriber that records all events, and the buffer it records
+/// into.
+///
+/// The subscriber is meant to be installed for the duration of a test via
+/// [`tracing::subscriber::with_default`]:
+///
+/// ```
+/// # use rust_tracing::utils::capture::with_capture;
+/// let (subscriber, events) = with_capture();
+/// tracing::subscriber::with_default(subscriber, || {
+///     tracing::warn!(attempt = 3, "retrying");
+/// });
+///
+/// let events = events.lock().unwrap();
+/// assert_eq!(events[0].level, tracing::Level::WARN);
+/// assert_eq!(events[0].message.as_deref(), Some("retrying"));
+/// assert_eq!(events[0].field("attempt"), Some("3"));
+/// ```
+pub fn with_capture() -> (impl Subscriber + Send + Sync, CapturedEvents) {
+    let layer = CaptureLayer::new();
+    let events = layer.events();
+    (Registry::default().with(layer), events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn captures_events() {
+        let (subscriber, events) = with_capture();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "app::db", table = "users", rows = 2, "query done");
+            tracing::error!(reason = ?Some(1), "failed");
+        });
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events[0],
+            CapturedEvent {
+                level: Level::INFO,
+                target: "app::db".to_owned(),
+                message: Some("query done".to_owned()),
+                fields: vec![
+                    ("table".to_owned(), "users".to_owned()),
+                    ("rows".to_owned(), "2".to_owned()),
+                ],
+            }
+        );
+        assert_eq!(events[1].level, Level::ERROR);
+        assert_eq!(events[1].field("reason"), Some("Some(1)"));
+        assert_eq!(events.len(), 2);
+    }
+}
@@ -0,0 +1,195 @@
+use metrics::Label;
+use tracing::{
+    Event,
+    Subscriber,
+    field::{
+        Field,
+        Visit,
+    },
+};
+use tracing_subscriber::{
+    Layer,
+    layer::Context,
+};
+
+/// Field naming the counter to increment.
+const COUNTER: &str = "metric.counter";
+/// Field naming the gauge to set.
+const GAUGE: &str = "metric.gauge";
+/// Field naming the histogram to record into.
+const HISTOGRAM: &str = "metric.histogram";
+/// Field holding the value to record.
+const VALUE: &str = "value";
+/// Prefix of fields recorded as metric labels.
+const LABEL_PREFIX: &str = "label.";
+
+/// The metric named by an event.
+#[derive(Debug)]
+enum Metric {
+    Counter(String),
+    Gauge(String),
+    Histogram(String),
+}
+
+/// Collects the metric fields of an event.
+#[derive(Debug, Default)]
+struct MetricVisitor {
+    metric: Option<Metric>,
+    value: Option<f64>,
+    labels: Vec<Label>,
+}
+
+impl Visit for MetricVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let name = value.to_owned();
+        match field.name() {
+            COUNTER => self.metric = Some(Metric::Counter(name)),
+            GAUGE => self.metric = Some(Metric::Gauge(name)),
+            HISTOGRAM => self.metric = Some(Metric::Histogram(name)),
+            field => {
+                if let Some(key) = field.strip_prefix(LABEL_PREFIX) {
+                    self.labels.push(Label::new(key.to_owned(), name));
+                }
+            }
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == VALUE {
+            self.value = Some(value);
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_f64(field, value as f64);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_f64(field, value as f64);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+        if let Some(key) = field.name().strip_prefix(LABEL_PREFIX) {
+            self.labels
+                .push(Label::new(key.to_owned(), format!("{value:?}")));
+        }
+    }
+}
+
+/// A [`Layer`] that records metrics declared on events, via the [`metrics`]
+/// facade.
+///
+/// Events naming a metric via a `metric.counter`, `metric.gauge` or
+/// `metric.histogram` field record the numeric `value` field into that
+/// metric. Counters are incremented by `value`, or by 1 if absent. Gauges are
+/// set to, and histograms record, `value`, and are skipped if it is absent.
+/// Fields prefixed with `label.` are recorded as labels.
+///
+/// ```
+/// tracing::info!(metric.counter = "requests", value = 1, label.route = "/");
+/// tracing::info!(metric.gauge = "in_flight", value = 7.0);
+/// tracing::info!(metric.histogram = "latency_seconds", value = 0.25);
+/// ```
+///
+/// The layer is not installed by [`init_tracing`]. Add it to the subscriber
+/// returned by [`build_registry`]:
+///
+/// ```no_run
+/// # use rust_tracing::utils::{event_metrics::MetricsLayer, tracing::build_registry};
+/// use tracing_subscriber::{
+///     layer::SubscriberExt,
+///     util::SubscriberInitExt,
+/// };
+///
+/// let (registry, guard) = build_registry();
+/// registry.with(MetricsLayer).init();
+/// ```
+///
+/// [`init_tracing`]: crate::utils::tracing::init_tracing
+/// [`build_registry`]: crate::utils::tracing::build_registry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsLayer;
+
+impl<S: Subscriber> Layer<S> for MetricsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if !event
+            .metadata()
+            .fields()
+            .iter()
+            .any(|f| f.name().starts_with("metric."))
+        {
+            return;
+        }
+
+        let mut visitor = MetricVisitor::default();
+        event.record(&mut visitor);
+        let MetricVisitor {
+            metric: Some(metric),
+            value,
+            labels,
+        } = visitor
+        else {
+            return;
+        };
+
+        match (metric, value) {
+            (Metric::Counter(name), value) => {
+                metrics::counter!(name, labels).increment(value.map_or(1, |v| v as u64))
+            }
+            (Metric::Gauge(name), Some(value)) => metrics::gauge!(name, labels).set(value),
+            (Metric::Histogram(name), Some(value)) => {
+                metrics::histogram!(name, labels).record(value)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn render(f: impl FnOnce()) -> String {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let subscriber = tracing_subscriber::registry().with(MetricsLayer);
+
+        metrics::with_local_recorder(&recorder, || {
+            tracing::subscriber::with_default(subscriber, f);
+        });
+        handle.render()
+    }
+
+    #[test]
+    fn counter() {
+        let rendered = render(|| {
+            tracing::info!(metric.counter = "requests", value = 2, label.route = "/");
+            tracing::info!(metric.counter = "requests", label.route = "/");
+            tracing::info!(counter = "ignored", value = 5);
+        });
+        assert!(rendered.contains(r#"requests{route="/"} 3"#), "{rendered}");
+        assert!(!rendered.contains("ignored"), "{rendered}");
+    }
+
+    #[test]
+    fn gauge() {
+        let rendered = render(|| {
+            tracing::info!(metric.gauge = "in_flight", value = 7.5);
+            tracing::info!(metric.gauge = "skipped");
+        });
+        assert!(rendered.contains("in_flight 7.5"), "{rendered}");
+        assert!(!rendered.contains("skipped"), "{rendered}");
+    }
+
+    #[test]
+    fn histogram() {
+        let rendered = render(|| {
+            tracing::info!(metric.histogram = "latency", value = 1.5);
+            tracing::info!(metric.histogram = "latency", value = 0.5);
+        });
+        assert!(rendered.contains("latency_count 2"), "{rendered}");
+        assert!(rendered.contains("latency_sum 2"), "{rendered}");
+    }
+}
@@ -0,0 +1,103 @@
+use crate::utils::from_env::{
+    FromEnvErr,
+    FromEnvVar,
+    parse_env_if_present,
+};
+use tracing_subscriber::{
+    EnvFilter,
+    filter::ParseError,
+};
+
+/// Validated [`EnvFilter`] directives, e.g. `mycrate=debug,hyper=warn`, in
+/// the same syntax as `RUST_LOG`.
+///
+/// [`EnvFilter`] is neither [`Clone`] nor comparable, so the directives are
+/// kept as a string, and a fresh filter is built from them on demand.
+///
+/// ```
+/// # use rust_tracing::utils::filter::LogFilter;
+/// let filter: LogFilter = "mycrate=debug,hyper=warn".parse().unwrap();
+/// assert_eq!(filter.directives(), "mycrate=debug,hyper=warn");
+/// assert!("mycrate=notalevel".parse::<LogFilter>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    directives: String,
+}
+
+impl LogFilter {
+    /// Parse the directives, failing if any of them is invalid.
+    pub fn new(directives: impl Into<String>) -> Result<Self, ParseError> {
+        let directives = directives.into();
+        EnvFilter::try_new(&directives)?;
+        Ok(Self { directives })
+    }
+
+    /// The directives, as given.
+    pub fn directives(&self) -> &str {
+        &self.directives
+    }
+
+    /// Build an [`EnvFilter`] from the directives.
+    pub fn to_env_filter(&self) -> EnvFilter {
+        EnvFilter::new(&self.directives)
+    }
+}
+
+impl core::fmt::Display for LogFilter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.directives)
+    }
+}
+
+impl core::str::FromStr for LogFilter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl From<LogFilter> for EnvFilter {
+    fn from(filter: LogFilter) -> Self {
+        filter.to_env_filter()
+    }
+}
+
+impl FromEnvVar for LogFilter {
+    type Error = ParseError;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_env_if_present(env_var)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_env_var() {
+        unsafe {
+            std::env::set_var("LOG_FILTER_VALID", "mycrate=debug,hyper=warn");
+            std::env::set_var("LOG_FILTER_INVALID", "mycrate=notalevel");
+            std::env::set_var("LOG_FILTER_EMPTY", "");
+        }
+
+        let filter = LogFilter::from_env_var("LOG_FILTER_VALID").unwrap();
+        assert_eq!(filter.directives(), "mycrate=debug,hyper=warn");
+        assert_eq!(
+            filter.to_env_filter().to_string(),
+            "mycrate=debug,hyper=warn"
+        );
+
+        assert!(matches!(
+            LogFilter::from_env_var("LOG_FILTER_INVALID"),
+            Err(FromEnvErr::ParseError(_))
+        ));
+        assert!(matches!(
+            LogFilter::from_env_var("LOG_FILTER_EMPTY"),
+            Err(FromEnvErr::Empty(_))
+        ));
+    }
+}
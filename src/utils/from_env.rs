@@ -1,8 +1,18 @@
 use std::{
+    any::TypeId,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
     convert::Infallible,
     env::VarError,
     num::ParseIntError,
     str::FromStr,
+    sync::{
+        Mutex,
+        OnceLock,
+        PoisonError,
+    },
 };
 
 #[cfg(not(doctest))]
@@ -12,7 +22,8 @@ use std::{
 /// applied to. It will generate a `from_env` function that loads the struct
 /// from the environment. It will also generate an `inventory` function that
 /// returns a list of all environment variables that are required to load the
-/// struct.
+/// struct, memoized by `cached_inventory`, and a `from_env_with_present_vars`
+/// function that loads the struct along with those of them that are set.
 ///
 /// The macro also generates a `____EnvError` type that captures errors that can
 /// occur when trying to create an instance of the struct from environment
@@ -36,9 +47,10 @@ use std::{
 /// - `var = ""`: The name of the environment variable. **This is required if
 ///   the prop implements [`FromEnvVar`] and forbidden if the prop implements
 ///   [`FromEnv`].**
-/// - `desc = ""`: A description of the environment variable. **This is required
-///   if the prop implements [`FromEnvVar`] and forbidden if the prop
-///   implements [`FromEnv`].**
+/// - `desc = ""`: A description of the environment variable. Defaults to the
+///   prop's doc comment, joined into a single line. **One of the two is
+///   required if the prop implements [`FromEnvVar`], and must not be empty.
+///   `desc` is forbidden if the prop implements [`FromEnv`].**
 /// - `optional`: Marks the prop as optional. This is currently only used in the
 ///   generated `fn inventory`, and is informational.
 /// - `infallible`: Marks the prop as infallible. This means that the prop
@@ -46,6 +58,36 @@ use std::{
 /// - `skip`: Marks the prop as skipped. This means that the prop will not be
 ///   loaded from the environment, and will be generated via
 ///   `Default::default()` instead.
+/// - `json`: Deserializes the environment variable value as JSON into the prop
+///   type, which must implement [`serde::Deserialize`]. The prop does not
+///   need to implement [`FromEnvVar`]. Requires the `json` feature.
+/// - `delimiter = ";"`: Splits a [`Vec`] prop on the given delimiter rather
+///   than `,`. See [`from_env_var_delimited`].
+/// - `allow_empty`: Accepts an environment variable that is set to the empty
+///   string, producing `Default::default()` instead of [`FromEnvErr::Empty`].
+///   The prop type must implement [`Default`].
+/// - `group = ""`: Makes the environment variable mutually exclusive with
+///   those of the other props in the same group. Loading fails with
+///   [`FromEnvErr::Conflict`] if more than one of them is set. The props are
+///   usually also `optional`.
+/// - `requires = "OTHER_VAR"`: Requires the named environment variable to be
+///   set whenever this prop's environment variable is set. Loading fails with
+///   [`FromEnvErr::MissingDependency`] otherwise. May be repeated. This is
+///   meant for `optional` props, e.g. a TLS certificate that is useless
+///   without its key. On a required prop, the named variable is effectively
+///   required too.
+/// - `alias = "OLD_VAR"`: Reads the named environment variable if this prop's
+///   environment variable is not set, e.g. after a rename. May be repeated,
+///   in which case the first alias that is set is read. The inventory lists
+///   the aliases in [`EnvItemInfo::aliases`].
+/// - `deprecated = "use NEW_VAR instead"`: Logs a warning with the given
+///   message, once per process, when a deprecated environment variable is
+///   read. With `alias`, the aliases are deprecated. Without, the prop's own
+///   environment variable is.
+/// - `secret`: Marks the environment variable as secret in the inventory, so
+///   that its value is redacted by [`effective_vars`]. Props of type
+///   [`Redacted`] are secret without it, and are also redacted from the
+///   resolved values logged by [`FromEnv::log_effective`].
 ///
 /// ## Conditions of use
 ///
@@ -54,15 +96,24 @@ use std::{
 /// - Struct props MUST implement either [`FromEnvVar`] or [`FromEnv`].
 /// - If the prop implements [`FromEnvVar`], it must be tagged as follows:
 ///     - `var = "ENV_VAR_NAME"`: The environment variable name to load.
-///     - `desc = "description"`: A description of the environment variable.
+///     - `desc = "description"`: A description of the environment variable,
+///       unless the prop has a doc comment.
 /// - If the prop is an [`Option<T>`], it must be tagged as follows:
 ///     - `optional`
+///
+///   unless `T` implements [`FromEnv`], in which case the prop is [`None`] if
+///   none of the environment variables of `T` is set, and fails to load if
+///   only some of the required ones are.
 /// - If the prop's associated error type is [`Infallible`], it must be tagged
 ///   as follows:
 ///     - `infallible`
 /// - If used within this crate (`rust_tracing`), the entire struct must be
 ///   tagged with `#[from_env(crate)]` (see the [`SlotCalculator`] for an
 ///   example).
+/// - The struct may have type and const parameters, but not lifetimes. Props
+///   whose type mentions a type parameter are bounded by [`FromEnv`] or
+///   [`FromEnvVar`] in the generated impl, as appropriate. `json` and
+///   delimited props are not, and must be bounded on the struct instead.
 ///
 /// # Examples
 ///
@@ -116,6 +167,14 @@ use std::{
 /// # }
 /// ```
 ///
+/// The generated impl loads every prop before failing, so that
+/// [`FromEnv::from_env_all`] reports the errors of all of them.
+///
+/// The generated impl also supports [`FromEnv::from_env_prefixed`], so that
+/// e.g. `MyBiggerCfg::from_env_prefixed("TENANT_A_")` reads
+/// `TENANT_A_BIGGGG_CONFIGGGG` and `TENANT_A_COOL_DUDE`. Aliases, `group` and
+/// `requires` checks use the prefixed names too.
+///
 /// This will generate a [`FromEnv`] implementation for the struct, and a
 /// `MyCfgEnvError` type that is used to represent errors that can occur when
 /// loading from the environment. The error generated will look like this:
@@ -137,7 +196,21 @@ pub use init4_from_env_derive::FromEnv;
 /// Details about an environment variable. This is used to generate
 /// documentation for the environment variables and by the [`FromEnv`] trait to
 /// check if necessary environment variables are present.
+///
+/// The struct is non-exhaustive, so that details may be added without
+/// breaking downstream code. Outside this crate, create it with
+/// [`EnvItemInfo::new`]. In a `'static` context, e.g. an inventory, use an
+/// inline `const` block:
+///
+/// ```
+/// # use rust_tracing::utils::from_env::EnvItemInfo;
+/// let inventory: Vec<&'static EnvItemInfo> = vec![
+///     const { &EnvItemInfo::new("PORT", "The port to listen on", false) },
+///     const { &EnvItemInfo::new("TOKEN", "The API token", true).with_secret(true) },
+/// ];
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct EnvItemInfo {
     /// The environment variable name.
     pub var: &'static str,
@@ -145,11 +218,86 @@ pub struct EnvItemInfo {
     pub description: &'static str,
     /// Whether the environment variable is optional or not.
     pub optional: bool,
+    /// Whether the value is secret, e.g. a password, and must not be logged.
+    pub secret: bool,
+    /// Deprecated names of the environment variable, read if it is not set.
+    pub aliases: &'static [&'static str],
+}
+
+impl EnvItemInfo {
+    /// Details of an environment variable that is not secret.
+    pub const fn new(var: &'static str, description: &'static str, optional: bool) -> Self {
+        Self {
+            var,
+            description,
+            optional,
+            secret: false,
+            aliases: &[],
+        }
+    }
+
+    /// Set whether the value is secret.
+    pub const fn with_secret(mut self, secret: bool) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// Set the deprecated names of the environment variable.
+    pub const fn with_aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// The environment variable name followed by its aliases.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.var).chain(self.aliases.iter().copied())
+    }
+}
+
+/// Warn about the set environment variables starting with `prefix` that are
+/// not in the inventory, e.g. typos such as `OTEL_ENDPONT`, which would
+/// otherwise be silently ignored. Returns their names, sorted.
+///
+/// The warnings are emitted as [`tracing`] events, so tracing should be
+/// initialized first.
+pub fn warn_unknown_vars(prefix: &str, inventory: &[&EnvItemInfo]) -> Vec<String> {
+    let mut unknown: Vec<String> = std::env::vars_os()
+        .filter_map(|(var, _)| var.into_string().ok())
+        .filter(|var| {
+            var.starts_with(prefix) && !inventory.iter().any(|item| item.names().any(|n| n == var))
+        })
+        .collect();
+    unknown.sort();
+    for var in &unknown {
+        tracing::warn!(var, "unknown environment variable, it is ignored");
+    }
+    unknown
+}
+
+/// The values of the set environment variables in the inventory, by name,
+/// with the values of secret variables replaced by `[REDACTED]`. Unset
+/// variables are omitted, as their defaults apply. Set aliases are included
+/// under their own names.
+pub fn effective_vars(inventory: &[&EnvItemInfo]) -> BTreeMap<&'static str, String> {
+    inventory
+        .iter()
+        .flat_map(|item| item.names().map(move |var| (item, var)))
+        .filter_map(|(item, var)| {
+            let value = std::env::var_os(var)?;
+            let value = if item.secret {
+                REDACTED.to_owned()
+            } else {
+                value.to_string_lossy().into_owned()
+            };
+            Some((var, value))
+        })
+        .collect()
 }
 
 /// Error type for loading from the environment. See the [`FromEnv`] trait for
 /// more information.
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum FromEnvErr<Inner> {
     /// The environment variable is missing.
     #[error("cannot read variable {0}: {1}")]
@@ -160,6 +308,28 @@ pub enum FromEnvErr<Inner> {
     /// The environment variable is present, but the value could not be parsed.
     #[error("failed to parse environment variable {0}")]
     ParseError(#[from] Inner),
+    /// More than one of a group of mutually exclusive environment variables
+    /// is set.
+    #[error("environment variables {} are mutually exclusive", .0.join(", "))]
+    Conflict(Vec<String>),
+    /// An environment variable is set, but another it requires is not.
+    #[error("environment variable {requires} is required when {var} is set")]
+    MissingDependency {
+        /// The variable that is set.
+        var: String,
+        /// The variable it requires, which is not set.
+        requires: String,
+    },
+    /// The config was loaded with a prefix, but reads fixed variable names,
+    /// e.g. the standard OpenTelemetry ones. See
+    /// [`FromEnv::from_env_prefixed`].
+    #[error("{config} does not support prefixed environment variables, got prefix {prefix:?}")]
+    PrefixUnsupported {
+        /// The type name of the config.
+        config: String,
+        /// The prefix it was loaded with.
+        prefix: String,
+    },
 }
 
 impl FromEnvErr<Infallible> {
@@ -169,6 +339,13 @@ impl FromEnvErr<Infallible> {
             Self::EnvError(s, e) => FromEnvErr::EnvError(s, e),
             Self::Empty(s) => FromEnvErr::Empty(s),
             Self::ParseError(_) => unreachable!(),
+            Self::Conflict(vars) => FromEnvErr::Conflict(vars),
+            Self::MissingDependency { var, requires } => {
+                FromEnvErr::MissingDependency { var, requires }
+            }
+            Self::PrefixUnsupported { config, prefix } => {
+                FromEnvErr::PrefixUnsupported { config, prefix }
+            }
         }
     }
 }
@@ -183,6 +360,13 @@ impl<Inner> FromEnvErr<Inner> {
             FromEnvErr::EnvError(s, e) => Self::EnvError(s, e),
             FromEnvErr::Empty(s) => Self::Empty(s),
             FromEnvErr::ParseError(e) => Self::ParseError(Inner::from(e)),
+            FromEnvErr::Conflict(vars) => Self::Conflict(vars),
+            FromEnvErr::MissingDependency { var, requires } => {
+                Self::MissingDependency { var, requires }
+            }
+            FromEnvErr::PrefixUnsupported { config, prefix } => {
+                Self::PrefixUnsupported { config, prefix }
+            }
         }
     }
 
@@ -194,6 +378,13 @@ impl<Inner> FromEnvErr<Inner> {
             Self::EnvError(s, e) => FromEnvErr::EnvError(s, e),
             Self::Empty(s) => FromEnvErr::Empty(s),
             Self::ParseError(e) => FromEnvErr::ParseError(f(e)),
+            Self::Conflict(vars) => FromEnvErr::Conflict(vars),
+            Self::MissingDependency { var, requires } => {
+                FromEnvErr::MissingDependency { var, requires }
+            }
+            Self::PrefixUnsupported { config, prefix } => {
+                FromEnvErr::PrefixUnsupported { config, prefix }
+            }
         }
     }
 
@@ -211,6 +402,118 @@ impl<Inner> FromEnvErr<Inner> {
     pub const fn parse_error(err: Inner) -> Self {
         Self::ParseError(err)
     }
+
+    /// Mutually exclusive env vars set together.
+    pub fn conflict(vars: &[&str]) -> Self {
+        Self::Conflict(vars.iter().map(ToString::to_string).collect())
+    }
+
+    /// Env var set without another env var it requires.
+    pub fn missing_dependency(var: &str, requires: &str) -> Self {
+        Self::MissingDependency {
+            var: var.to_string(),
+            requires: requires.to_string(),
+        }
+    }
+
+    /// Config of type `T` loaded with a prefix it does not support.
+    pub fn prefix_unsupported<T: ?Sized>(prefix: &str) -> Self {
+        Self::PrefixUnsupported {
+            config: core::any::type_name::<T>().to_string(),
+            prefix: prefix.to_string(),
+        }
+    }
+
+    /// The name of the environment variable, if known. Parse errors do not
+    /// record the variable name, conflicts involve several, and unsupported
+    /// prefixes none. For missing dependencies, this is the missing variable.
+    pub fn var(&self) -> Option<&str> {
+        match self {
+            Self::EnvError(var, _) | Self::Empty(var) => Some(var),
+            Self::MissingDependency { requires, .. } => Some(requires),
+            Self::ParseError(_) | Self::Conflict(_) | Self::PrefixUnsupported { .. } => None,
+        }
+    }
+
+    /// Render the error over multiple lines, including the variable name, its
+    /// description from the inventory, and the chain of causes.
+    ///
+    /// ```
+    /// # use rust_tracing::utils::from_env::{EnvItemInfo, FromEnvErr};
+    /// let inventory = vec![const { &EnvItemInfo::new("PORT", "The port to listen on", false) }];
+    /// let err = FromEnvErr::<std::num::ParseIntError>::empty("PORT");
+    /// assert_eq!(
+    ///     err.report(&inventory).to_string(),
+    ///     "environment variable is empty\n  \
+    ///      variable: PORT\n  \
+    ///      description: The port to listen on"
+    /// );
+    /// ```
+    pub const fn report<'a>(
+        &'a self,
+        inventory: &'a [&'static EnvItemInfo],
+    ) -> FromEnvErrReport<'a, Inner> {
+        FromEnvErrReport {
+            err: self,
+            inventory,
+        }
+    }
+}
+
+/// Multiline rendering of a [`FromEnvErr`]. See [`FromEnvErr::report`].
+#[derive(Debug)]
+pub struct FromEnvErrReport<'a, Inner> {
+    err: &'a FromEnvErr<Inner>,
+    inventory: &'a [&'static EnvItemInfo],
+}
+
+impl<Inner> core::fmt::Display for FromEnvErrReport<'_, Inner>
+where
+    Inner: core::error::Error,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut source: Option<&dyn core::error::Error> = match self.err {
+            FromEnvErr::EnvError(_, err) => {
+                f.write_str("cannot read environment variable")?;
+                Some(err)
+            }
+            FromEnvErr::Empty(_) => {
+                f.write_str("environment variable is empty")?;
+                None
+            }
+            FromEnvErr::ParseError(err) => {
+                f.write_str("failed to parse environment variable")?;
+                Some(err)
+            }
+            FromEnvErr::Conflict(vars) => {
+                f.write_str("environment variables are mutually exclusive")?;
+                write!(f, "\n  variables: {}", vars.join(", "))?;
+                None
+            }
+            FromEnvErr::MissingDependency { var, .. } => {
+                f.write_str("environment variable is required")?;
+                write!(f, "\n  required by: {var}")?;
+                None
+            }
+            FromEnvErr::PrefixUnsupported { config, prefix } => {
+                f.write_str("prefixed environment variables are not supported")?;
+                write!(f, "\n  config: {config}\n  prefix: {prefix}")?;
+                None
+            }
+        };
+
+        if let Some(var) = self.err.var() {
+            write!(f, "\n  variable: {var}")?;
+            if let Some(item) = self.inventory.iter().find(|item| item.var == var) {
+                write!(f, "\n  description: {}", item.description)?;
+            }
+        }
+        while let Some(err) = source {
+            write!(f, "\n  cause: {err}")?;
+            source = err.source();
+        }
+        Ok(())
+    }
 }
 
 /// Convenience function for parsing a value from the environment, if present
@@ -225,6 +528,188 @@ pub fn parse_env_if_present<T: FromStr>(env_var: &str) -> Result<T, FromEnvErr<T
     }
 }
 
+/// Error produced when a comma-separated `key=value` map fails to parse. See
+/// the [`FromEnvVar`] impls for [`HashMap`] and [`BTreeMap`].
+///
+/// `E` is the parse error of the values, and `K` that of the keys, if they
+/// are parsed.
+///
+/// [`HashMap`]: std::collections::HashMap
+/// [`BTreeMap`]: std::collections::BTreeMap
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MapParseError<E, K = Infallible> {
+    /// An entry is missing the `=` separator.
+    #[error("missing `=` in map entry {0}")]
+    MissingSeparator(String),
+    /// The value for a key could not be parsed.
+    #[error("invalid value for key {key}: {error}")]
+    Value {
+        /// The key whose value failed to parse.
+        key: String,
+        /// The parse error.
+        #[source]
+        error: E,
+    },
+    /// A key could not be parsed.
+    #[error("invalid key {key}: {error}")]
+    Key {
+        /// The key that failed to parse.
+        key: String,
+        /// The parse error.
+        #[source]
+        error: K,
+    },
+    /// A key appears more than once.
+    #[error("duplicate map key {0}")]
+    DuplicateKey(String),
+}
+
+/// Error produced when a `start..end` or `start..=end` range fails to parse.
+/// See the [`FromEnvVar`] impls for [`Range`] and [`RangeInclusive`].
+///
+/// [`Range`]: std::ops::Range
+/// [`RangeInclusive`]: std::ops::RangeInclusive
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RangeParseError<E> {
+    /// The value is missing the `..` or `..=` separator.
+    #[error("missing `{separator}` in range {range}")]
+    MissingSeparator {
+        /// The expected separator.
+        separator: &'static str,
+        /// The malformed range.
+        range: String,
+    },
+    /// The start or end of the range is empty.
+    #[error("missing bound in range {0}")]
+    MissingBound(String),
+    /// A bound could not be parsed.
+    #[error("invalid range bound: {0}")]
+    Bound(#[source] E),
+    /// The start of the range is greater than the end.
+    #[error("range start is greater than end in {0}")]
+    Inverted(String),
+}
+
+/// Error produced when a comma-separated fixed-size array fails to parse. See
+/// the [`FromEnvVar`] impl for arrays.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ArrayParseError<E> {
+    /// The number of elements does not match the array length.
+    #[error("wrong number of array elements, expected {expected}, got {got}")]
+    Length {
+        /// The array length.
+        expected: usize,
+        /// The number of elements given.
+        got: usize,
+    },
+    /// An element could not be parsed.
+    #[error("invalid array element at index {index}: {error}")]
+    Element {
+        /// The index of the element.
+        index: usize,
+        /// The parse error.
+        #[source]
+        error: E,
+    },
+}
+
+/// Error produced when a comma-separated tuple fails to parse. See the
+/// [`FromEnvVar`] impls for tuples.
+///
+/// The elements of a tuple may have different error types, so element errors
+/// are boxed.
+#[derive(Debug, thiserror::Error)]
+pub enum TupleParseError {
+    /// The number of elements does not match the tuple arity.
+    #[error("wrong number of tuple elements, expected {expected}, got {got}")]
+    Length {
+        /// The tuple arity.
+        expected: usize,
+        /// The number of elements given.
+        got: usize,
+    },
+    /// An element could not be parsed.
+    #[error("invalid tuple element at index {index}: {error}")]
+    Element {
+        /// The index of the element.
+        index: usize,
+        /// The parse error.
+        #[source]
+        error: Box<dyn core::error::Error + Send + Sync>,
+    },
+}
+
+/// Error produced when a tuple of [`FromEnv`] configs fails to load. See the
+/// [`FromEnv`] impls for tuples.
+///
+/// Each variant holds the error of the config at that position. Unused
+/// positions of shorter tuples are [`Infallible`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TupleEnvError<A, B, C = Infallible, D = Infallible> {
+    /// The first config failed to load.
+    #[error(transparent)]
+    First(A),
+    /// The second config failed to load.
+    #[error(transparent)]
+    Second(B),
+    /// The third config failed to load.
+    #[error(transparent)]
+    Third(C),
+    /// The fourth config failed to load.
+    #[error(transparent)]
+    Fourth(D),
+}
+
+/// Error produced when a boolean environment variable is not one of the
+/// accepted values. See the [`FromEnvVar`] impl for [`bool`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid boolean: {0}, expected true/false, 1/0, yes/no or on/off")]
+pub struct BoolParseError(String);
+
+/// Error produced when a JSON-encoded environment variable fails to
+/// deserialize. See [`parse_json_env`].
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonParseError(String);
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for JsonParseError {
+    fn from(err: serde_json::Error) -> Self {
+        Self(err.to_string())
+    }
+}
+
+#[cfg(feature = "json")]
+impl core::fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid JSON: {}", self.0)
+    }
+}
+
+#[cfg(feature = "json")]
+impl core::error::Error for JsonParseError {}
+
+/// Convenience function for deserializing a JSON-encoded value from the
+/// environment, if present and non-empty.
+///
+/// This is used by the `#[from_env(json)]` attribute of the
+/// [`FromEnv`](macro@FromEnv) derive macro.
+#[cfg(feature = "json")]
+pub fn parse_json_env<T>(env_var: &str) -> Result<T, FromEnvErr<JsonParseError>>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+
+    if s.is_empty() {
+        Err(FromEnvErr::empty(env_var))
+    } else {
+        serde_json::from_str(&s)
+            .map_err(JsonParseError::from)
+            .map_err(Into::into)
+    }
+}
+
 /// Trait for loading from the environment.
 ///
 /// This trait is for structs or other complex objects, that need to be loaded
@@ -273,6 +758,31 @@ pub fn parse_env_if_present<T: FromStr>(env_var: &str) -> Result<T, FromEnvErr<T
 ///    type Error = MyTypeErr;
 /// }
 /// ```
+///
+/// ## Propagating errors
+///
+/// [`FromEnvErr<T>`] implements [`Error`] + [`Send`] + [`Sync`] +
+/// `'static` whenever `T` does. This holds for the error types generated by
+/// the derive macro, as long as the error types of the props do. Load errors
+/// may therefore be propagated with `?` into `anyhow::Result`,
+/// `eyre::Result` or a boxed error, keeping the message of the failing
+/// variable:
+///
+/// ```
+/// # use rust_tracing::utils::from_env::FromEnv;
+/// # #[derive(Debug, FromEnv)]
+/// # #[from_env(rust_tracing)]
+/// # pub struct AppCfg {
+/// #     #[from_env(var = "APP_THREADS", desc = "Worker threads")]
+/// #     threads: u8,
+/// # }
+/// fn load() -> Result<AppCfg, Box<dyn std::error::Error + Send + Sync>> {
+///     Ok(AppCfg::from_env()?)
+/// }
+/// # fn main() {}
+/// ```
+///
+/// [`Error`]: core::error::Error
 pub trait FromEnv: core::fmt::Debug + Sized + 'static {
     /// Error type produced when loading from the environment.
     type Error: core::error::Error + Clone;
@@ -285,6 +795,26 @@ pub trait FromEnv: core::fmt::Debug + Sized + 'static {
     /// struct, including optional vars.
     fn inventory() -> Vec<&'static EnvItemInfo>;
 
+    /// Get the [`FromEnv::inventory`], built once and shared by all calls, so
+    /// that repeated calls, e.g. to check or document composite configs, do
+    /// not rebuild it.
+    ///
+    /// The default implementation caches the inventory per type, for the
+    /// lifetime of the program. The derive macro memoizes it in the generated
+    /// impl instead, unless the struct is generic.
+    fn cached_inventory() -> &'static [&'static EnvItemInfo] {
+        type Cache = Mutex<HashMap<TypeId, &'static [&'static EnvItemInfo]>>;
+        // Statics are shared by all implementors, so the cache is keyed by
+        // type.
+        static CACHE: OnceLock<Cache> = OnceLock::new();
+        CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(TypeId::of::<Self>())
+            .or_insert_with(|| Self::inventory().leak())
+    }
+
     /// Get a list of missing environment variables.
     ///
     /// This will check all environment variables in the inventory, and return
@@ -292,7 +822,7 @@ pub trait FromEnv: core::fmt::Debug + Sized + 'static {
     /// reporting missing environment variables.
     fn check_inventory() -> Result<(), Vec<&'static EnvItemInfo>> {
         let mut missing = Vec::new();
-        for var in Self::inventory() {
+        for &var in Self::cached_inventory() {
             if std::env::var(var.var).is_err() && !var.optional {
                 missing.push(var);
             }
@@ -306,8 +836,150 @@ pub trait FromEnv: core::fmt::Debug + Sized + 'static {
 
     /// Load from the environment.
     fn from_env() -> Result<Self, FromEnvErr<Self::Error>>;
+
+    /// Warn about the set environment variables starting with `prefix` that
+    /// are not in the inventory, returning their names. See
+    /// [`warn_unknown_vars`].
+    fn warn_unknown(prefix: &str) -> Vec<String> {
+        warn_unknown_vars(prefix, Self::cached_inventory())
+    }
+
+    /// Log the loaded configuration as a single `INFO` event. The event
+    /// carries the resolved values, including defaults, as the [`Debug`]
+    /// output of the config, and the environment variables in the inventory
+    /// that are set, with the values of secret variables redacted. Calling it
+    /// right after initializing tracing gives a record of the configuration
+    /// the process actually uses. See [`effective_vars`].
+    ///
+    /// Secret variables are only redacted from the resolved values if their
+    /// props are [`Redacted`], or otherwise hide their value from [`Debug`].
+    ///
+    /// [`trace`] and [`init_tracing`] log the configs of this crate.
+    ///
+    /// [`Debug`]: core::fmt::Debug
+    /// [`trace`]: crate::trace
+    /// [`init_tracing`]: crate::utils::tracing::init_tracing
+    fn log_effective(&self) {
+        let vars = effective_vars(Self::cached_inventory());
+        tracing::info!(
+            config = core::any::type_name::<Self>(),
+            resolved = ?self,
+            ?vars,
+            "effective configuration"
+        );
+    }
+
+    /// Load from the environment, prepending `prefix` to the name of every
+    /// environment variable read, e.g. `TENANT_A_` to read `TENANT_A_PORT`
+    /// instead of `PORT`. This allows loading several instances of the same
+    /// config side by side.
+    ///
+    /// The derive macro passes the prefix down to nested [`FromEnv`] props,
+    /// and uses the prefixed names in errors. The inventory is unaffected.
+    ///
+    /// The default implementation calls [`FromEnv::from_env`], and so only
+    /// supports the empty prefix. Given any other, it returns
+    /// [`FromEnvErr::PrefixUnsupported`] rather than silently reading the
+    /// unprefixed variables. Hand-written implementations that read their
+    /// variables by name must override it to support prefixes.
+    fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        if !prefix.is_empty() {
+            return Err(FromEnvErr::prefix_unsupported::<Self>(prefix));
+        }
+        Self::from_env()
+    }
+
+    /// Load from the environment, reporting every error rather than only
+    /// the first, so that all missing or invalid variables can be fixed in
+    /// one go.
+    ///
+    /// ```
+    /// # use rust_tracing::utils::from_env::FromEnv;
+    /// # #[derive(Debug, FromEnv)]
+    /// # #[from_env(rust_tracing)]
+    /// # pub struct AppCfg {
+    /// #     #[from_env(var = "APP_THREADS", desc = "Worker threads")]
+    /// #     threads: u8,
+    /// # }
+    /// # fn main() {
+    /// if let Err(errors) = AppCfg::from_env_all() {
+    ///     for error in errors {
+    ///         eprintln!("{error}");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// The errors are never empty.
+    fn from_env_all() -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        Self::from_env_all_prefixed("")
+    }
+
+    /// Load from the environment with a prefix, as
+    /// [`FromEnv::from_env_prefixed`], reporting every error, as
+    /// [`FromEnv::from_env_all`].
+    ///
+    /// The default implementation reports only the error of
+    /// [`FromEnv::from_env_prefixed`].
+    fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        Self::from_env_prefixed(prefix).map_err(|e| vec![e])
+    }
+
+    /// Check whether the config can be loaded from the environment, reporting
+    /// every missing and every unparseable variable. Unlike
+    /// [`FromEnv::check_inventory`], which only checks that the required
+    /// variables are present, this parses them, making it a suitable
+    /// preflight for e.g. a `--check-config` subcommand.
+    ///
+    /// ```
+    /// # use rust_tracing::utils::from_env::FromEnv;
+    /// # #[derive(Debug, FromEnv)]
+    /// # #[from_env(rust_tracing)]
+    /// # pub struct AppCfg {
+    /// #     #[from_env(var = "APP_THREADS", desc = "Worker threads")]
+    /// #     threads: u8,
+    /// # }
+    /// # fn main() {
+    /// if let Err(errors) = AppCfg::validate_env() {
+    ///     let inventory = AppCfg::inventory();
+    ///     for error in errors {
+    ///         eprintln!("{}", error.report(&inventory));
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// Missing variables are reported as [`FromEnvErr::EnvError`] or
+    /// [`FromEnvErr::Empty`], and unparseable ones as
+    /// [`FromEnvErr::ParseError`]. The errors are never empty.
+    ///
+    /// This is only as thorough as [`FromEnv::from_env_all`]. For
+    /// hand-written implementations that don't override
+    /// [`FromEnv::from_env_all_prefixed`], only the first error is reported,
+    /// and variables that loading replaces by a default when invalid are not
+    /// checked at all. Loading may also have side effects, e.g. printing
+    /// warnings. The crate's own configs report every set variable that
+    /// fails to parse.
+    fn validate_env() -> Result<(), Vec<FromEnvErr<Self::Error>>> {
+        Self::from_env_all().map(drop)
+    }
+}
+
+/// Whether none of the environment variables in the inventory, or their
+/// aliases, is set to a non-empty value, with `prefix` prepended to their
+/// names.
+fn all_absent(prefix: &str, inventory: &[&EnvItemInfo]) -> bool {
+    inventory
+        .iter()
+        .flat_map(|item| item.names())
+        .all(|var| std::env::var_os(format!("{prefix}{var}")).is_none_or(|value| value.is_empty()))
 }
 
+/// An optional nested config is [`None`] if none of the environment variables
+/// in its inventory is set, or all of them are empty. Otherwise, it is loaded
+/// as usual, so that a partially configured subsystem, e.g. one with an
+/// endpoint but no credentials, fails to load instead of being disabled.
+/// Aliases count as set, so a config set only via deprecated names is loaded.
 impl<T> FromEnv for Option<T>
 where
     T: FromEnv,
@@ -319,15 +991,28 @@ where
     }
 
     fn check_inventory() -> Result<(), Vec<&'static EnvItemInfo>> {
+        if all_absent("", &T::inventory()) {
+            return Ok(());
+        }
         T::check_inventory()
     }
 
     fn from_env() -> Result<Self, FromEnvErr<Self::Error>> {
-        match T::from_env() {
-            Ok(v) => Ok(Some(v)),
-            Err(FromEnvErr::Empty(_)) | Err(FromEnvErr::EnvError(_, _)) => Ok(None),
-            Err(e) => Err(e),
+        Self::from_env_prefixed("")
+    }
+
+    fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        if all_absent(prefix, &T::inventory()) {
+            return Ok(None);
         }
+        T::from_env_prefixed(prefix).map(Some)
+    }
+
+    fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        if all_absent(prefix, &T::inventory()) {
+            return Ok(None);
+        }
+        T::from_env_all_prefixed(prefix).map(Some)
     }
 }
 
@@ -348,6 +1033,14 @@ where
     fn from_env() -> Result<Self, FromEnvErr<Self::Error>> {
         T::from_env().map(Box::new)
     }
+
+    fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        T::from_env_prefixed(prefix).map(Box::new)
+    }
+
+    fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        T::from_env_all_prefixed(prefix).map(Box::new)
+    }
 }
 
 impl<T> FromEnv for std::sync::Arc<T>
@@ -367,6 +1060,14 @@ where
     fn from_env() -> Result<Self, FromEnvErr<Self::Error>> {
         T::from_env().map(std::sync::Arc::new)
     }
+
+    fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        T::from_env_prefixed(prefix).map(std::sync::Arc::new)
+    }
+
+    fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        T::from_env_all_prefixed(prefix).map(std::sync::Arc::new)
+    }
 }
 
 impl<T, U> FromEnv for std::borrow::Cow<'static, U>
@@ -387,12 +1088,86 @@ where
     fn from_env() -> Result<Self, FromEnvErr<Self::Error>> {
         T::from_env().map(std::borrow::Cow::Owned)
     }
+
+    fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        T::from_env_prefixed(prefix).map(std::borrow::Cow::Owned)
+    }
+
+    fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        T::from_env_all_prefixed(prefix).map(std::borrow::Cow::Owned)
+    }
+}
+
+macro_rules! impl_from_env_for_tuple {
+    ($($t:ident $value:ident $variant:ident),+) => {
+        /// Loads each config in order, e.g. `(DbCfg, CacheCfg)` to load two
+        /// independent configs without a wrapper struct. The inventory is the
+        /// concatenation of theirs, and the prefix, if any, applies to all.
+        impl<$($t),+> FromEnv for ($($t,)+)
+        where
+            $($t: FromEnv,)+
+        {
+            type Error = TupleEnvError<$($t::Error),+>;
+
+            fn inventory() -> Vec<&'static EnvItemInfo> {
+                let mut inventory = Vec::new();
+                $(inventory.extend($t::inventory());)+
+                inventory
+            }
+
+            fn check_inventory() -> Result<(), Vec<&'static EnvItemInfo>> {
+                let mut missing = Vec::new();
+                $(missing.extend($t::check_inventory().err().unwrap_or_default());)+
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(missing)
+                }
+            }
+
+            fn from_env() -> Result<Self, FromEnvErr<Self::Error>> {
+                Self::from_env_prefixed("")
+            }
+
+            fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+                Ok(($(
+                    match $t::from_env_prefixed(prefix) {
+                        Ok(value) => value,
+                        Err(err) => return Err(err.map(TupleEnvError::$variant)),
+                    },
+                )+))
+            }
+
+            fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+                let mut errors = Vec::new();
+                $(
+                    let $value = match $t::from_env_all_prefixed(prefix) {
+                        Ok(value) => Some(value),
+                        Err(errs) => {
+                            errors.extend(errs.into_iter().map(|err| err.map(TupleEnvError::$variant)));
+                            None
+                        }
+                    };
+                )+
+                match ($($value,)+) {
+                    ($(Some($value),)+) => Ok(($($value,)+)),
+                    _ => Err(errors),
+                }
+            }
+        }
+    };
 }
 
+impl_from_env_for_tuple!(A a First, B b Second);
+impl_from_env_for_tuple!(A a First, B b Second, C c Third);
+impl_from_env_for_tuple!(A a First, B b Second, C c Third, D d Fourth);
+
 /// Trait for loading primitives from the environment. These are simple types
 /// that should correspond to a single environment variable. It has been
 /// implemented for common integer types, [`String`], [`url::Url`],
-/// [`tracing::Level`], and [`std::time::Duration`].
+/// [`tracing::Level`], [`tracing::level_filters::LevelFilter`],
+/// [`std::time::Duration`], [`std::time::SystemTime`], comma-separated `key=value` maps, and
+/// `start..end` or `start..=end` ranges.
 ///
 /// It aims to make [`FromEnv`] implementations easier to write, by providing a
 /// default implementation for common types.
@@ -550,11 +1325,80 @@ where
     }
 }
 
+/// Stand-in for secret values in logs.
+const REDACTED: &str = "[REDACTED]";
+
+/// A secret value, e.g. a token or password, whose [`Debug`] output is
+/// `[REDACTED]`, so that it does not leak when a config holding it is
+/// logged. It has no [`Display`] impl, so the value must be read explicitly
+/// via [`Redacted::expose`].
+///
+/// ```
+/// # use rust_tracing::utils::from_env::{FromEnvVar, Redacted};
+/// # unsafe { std::env::set_var("API_TOKEN", "hunter2") };
+/// let token: Redacted = Redacted::from_env_var("API_TOKEN").unwrap();
+/// assert_eq!(token.expose(), "hunter2");
+/// assert_eq!(format!("{token:?}"), "[REDACTED]");
+/// ```
+///
+/// [`Display`]: core::fmt::Display
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Redacted<T = String>(T);
+
+impl<T> Redacted<T> {
+    /// Wrap a secret value.
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Get the secret value.
+    pub const fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwrap the secret value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> core::fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> FromEnvVar for Redacted<T>
+where
+    T: FromEnvVar,
+{
+    type Error = T::Error;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        T::from_env_var(env_var).map(Self)
+    }
+}
+
+/// Produces [`FromEnvErr::Empty`] if the variable is set to the empty string,
+/// and [`FromEnvErr::EnvError`] if it is missing, consistent with other
+/// [`FromEnvVar`] impls. Use `#[from_env(allow_empty)]` on a derived field to
+/// accept the empty string.
 impl FromEnvVar for String {
     type Error = std::convert::Infallible;
 
     fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
-        std::env::var(env_var).map_err(|_| FromEnvErr::empty(env_var))
+        let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+        if s.is_empty() {
+            Err(FromEnvErr::empty(env_var))
+        } else {
+            Ok(s)
+        }
     }
 }
 
@@ -566,24 +1410,384 @@ impl FromEnvVar for std::time::Duration {
     }
 }
 
+/// Parses a unix timestamp, in whole seconds since the epoch. Negative values
+/// and timestamps the platform cannot represent are rejected.
+impl FromEnvVar for std::time::SystemTime {
+    type Error = SystemTimeParseError;
+
+    fn from_env_var(s: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        let secs = u64::from_env_var(s).map_err(FromEnvErr::from)?;
+        Self::UNIX_EPOCH
+            .checked_add(std::time::Duration::from_secs(secs))
+            .ok_or(FromEnvErr::parse_error(SystemTimeParseError::Overflow(
+                secs,
+            )))
+    }
+}
+
+/// Error parsing a [`SystemTime`] timestamp.
+///
+/// [`SystemTime`]: std::time::SystemTime
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SystemTimeParseError {
+    /// The value is not a number of seconds.
+    #[error(transparent)]
+    Int(#[from] ParseIntError),
+    /// The timestamp is out of the platform's range.
+    #[error("timestamp {0} is out of range")]
+    Overflow(u64),
+}
+
+/// A [`Duration`] written as a whole number with a unit suffix, one of `ms`,
+/// `s`, `m`, `h` or `d`, e.g. `500ms` or `5m`. A number without a suffix is
+/// in milliseconds, as for the [`FromEnvVar`] impl of [`Duration`].
+///
+/// ```
+/// # use rust_tracing::utils::from_env::HumanDuration;
+/// # use std::time::Duration;
+/// let timeout: HumanDuration = "5m".parse().unwrap();
+/// assert_eq!(Duration::from(timeout), Duration::from_secs(300));
+/// assert!("5 minutes".parse::<HumanDuration>().is_err());
+/// ```
+///
+/// [`Duration`]: std::time::Duration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(pub std::time::Duration);
+
+impl FromStr for HumanDuration {
+    type Err = DurationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (amount, unit) = s.split_at(split);
+        let amount: u64 = amount
+            .parse()
+            .map_err(|_| DurationParseError(s.to_owned()))?;
+        let secs = match unit.trim() {
+            "" | "ms" => return Ok(Self(std::time::Duration::from_millis(amount))),
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => return Err(DurationParseError(s.to_owned())),
+        };
+        amount
+            .checked_mul(secs)
+            .map(|secs| Self(std::time::Duration::from_secs(secs)))
+            .ok_or_else(|| DurationParseError(s.to_owned()))
+    }
+}
+
+impl From<HumanDuration> for std::time::Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromEnvVar for HumanDuration {
+    type Error = DurationParseError;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_env_if_present(env_var)
+    }
+}
+
+/// Error parsing a [`HumanDuration`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid duration {0}, expected e.g. `500ms`, `30s`, `5m` or `1h`")]
+pub struct DurationParseError(String);
+
+/// Parses comma-separated `key=value` pairs, e.g. `api=100,web=50`. Each value
+/// is parsed via [`FromStr`]. A duplicate key is an error, rather than
+/// overriding the earlier value. An empty variable produces an empty map.
+impl<V> FromEnvVar for std::collections::HashMap<String, V>
+where
+    V: FromStr + core::fmt::Debug + 'static,
+    V::Err: core::error::Error,
+{
+    type Error = MapParseError<V::Err>;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+        let mut map = Self::new();
+        if s.is_empty() {
+            return Ok(map);
+        }
+        for entry in s.split(',') {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| MapParseError::MissingSeparator(entry.to_string()))
+                .map_err(FromEnvErr::parse_error)?;
+            let key = key.trim().to_string();
+            let value = match value.trim().parse() {
+                Ok(value) => value,
+                Err(error) => {
+                    return Err(FromEnvErr::parse_error(MapParseError::Value { key, error }));
+                }
+            };
+            if map.contains_key(&key) {
+                return Err(FromEnvErr::parse_error(MapParseError::DuplicateKey(key)));
+            }
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+/// Parses comma-separated `key=value` pairs into an ordered map, e.g.
+/// `api=100,web=50`. Keys and values are parsed via [`FromStr`]. As for the
+/// [`HashMap`] impl, a duplicate key is an error. An empty variable produces
+/// an empty map.
+///
+/// [`HashMap`]: std::collections::HashMap
+impl<K, V> FromEnvVar for std::collections::BTreeMap<K, V>
+where
+    K: FromStr + Ord + core::fmt::Debug + 'static,
+    K::Err: core::error::Error + 'static,
+    V: FromStr + core::fmt::Debug + 'static,
+    V::Err: core::error::Error + 'static,
+{
+    type Error = MapParseError<V::Err, K::Err>;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+        let mut map = Self::new();
+        if s.is_empty() {
+            return Ok(map);
+        }
+        for entry in s.split(',') {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| MapParseError::MissingSeparator(entry.to_string()))
+                .map_err(FromEnvErr::parse_error)?;
+            let key = key.trim().to_string();
+            let parsed_key = match key.parse() {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    return Err(FromEnvErr::parse_error(MapParseError::Key { key, error }));
+                }
+            };
+            let value = match value.trim().parse() {
+                Ok(value) => value,
+                Err(error) => {
+                    return Err(FromEnvErr::parse_error(MapParseError::Value { key, error }));
+                }
+            };
+            if map.insert(parsed_key, value).is_some() {
+                return Err(FromEnvErr::parse_error(MapParseError::DuplicateKey(key)));
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Load a range of the form `start<separator>end` from the environment.
+fn parse_range_env<T>(
+    env_var: &str,
+    separator: &'static str,
+) -> Result<(T, T), FromEnvErr<RangeParseError<T::Err>>>
+where
+    T: FromStr + PartialOrd,
+{
+    let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+    if s.is_empty() {
+        return Err(FromEnvErr::empty(env_var));
+    }
+
+    let range = || s.clone();
+    let (start, end) = s
+        .split_once(separator)
+        .filter(|(_, end)| !end.starts_with('='))
+        .ok_or_else(|| {
+            RangeParseError::MissingSeparator {
+                separator,
+                range: range(),
+            }
+        })
+        .map_err(FromEnvErr::parse_error)?;
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() || end.is_empty() {
+        return Err(FromEnvErr::parse_error(RangeParseError::MissingBound(
+            range(),
+        )));
+    }
+
+    let start: T = start
+        .parse()
+        .map_err(|e| FromEnvErr::parse_error(RangeParseError::Bound(e)))?;
+    let end: T = end
+        .parse()
+        .map_err(|e| FromEnvErr::parse_error(RangeParseError::Bound(e)))?;
+    if start > end {
+        return Err(FromEnvErr::parse_error(RangeParseError::Inverted(range())));
+    }
+    Ok((start, end))
+}
+
+/// Parses an exclusive range, e.g. `8000..8100`. Errors if either bound is
+/// missing, or if the start is greater than the end.
+impl<T> FromEnvVar for std::ops::Range<T>
+where
+    T: FromStr + PartialOrd + core::fmt::Debug + 'static,
+    T::Err: core::error::Error,
+{
+    type Error = RangeParseError<T::Err>;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_range_env(env_var, "..").map(|(start, end)| start..end)
+    }
+}
+
+/// Parses an inclusive range, e.g. `8000..=8100`. Errors if either bound is
+/// missing, or if the start is greater than the end.
+impl<T> FromEnvVar for std::ops::RangeInclusive<T>
+where
+    T: FromStr + PartialOrd + core::fmt::Debug + 'static,
+    T::Err: core::error::Error,
+{
+    type Error = RangeParseError<T::Err>;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_range_env(env_var, "..=").map(|(start, end)| start..=end)
+    }
+}
+
+/// Convenience function for loading a list from the environment, split on
+/// the given delimiter, converting each element via [`TryFrom<String>`], as
+/// the [`FromEnvVar`] impl for [`Vec`] does. Loading fails with the error of
+/// the first element failing to convert. An empty variable produces an empty
+/// list.
+///
+/// This is used by the `#[from_env(delimiter = ";")]` attribute of the
+/// [`FromEnv`](macro@FromEnv) derive macro. The [`FromEnvVar`] impl for
+/// [`Vec`] splits on `,`.
+pub fn from_env_var_delimited<T>(
+    env_var: &str,
+    delimiter: &str,
+) -> Result<Vec<T>, FromEnvErr<T::Error>>
+where
+    T: TryFrom<String>,
+{
+    let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+    if s.is_empty() {
+        return Ok(vec![]);
+    }
+    s.split(delimiter)
+        .map(|item| T::try_from(item.to_owned()))
+        .collect::<Result<_, _>>()
+        .map_err(FromEnvErr::parse_error)
+}
+
+/// Parses comma-separated elements, converting each via [`TryFrom<String>`],
+/// e.g. into a validated newtype. Loading fails with the error of the first
+/// element failing to convert. An empty variable produces an empty list.
+///
+/// Types implementing [`From<String>`], e.g. [`String`], implement
+/// [`TryFrom<String>`] via the blanket impl in `core`, which takes precedence
+/// as a type cannot implement both. Their conversion cannot fail, so the
+/// error is [`Infallible`], and such props may be tagged `infallible`.
 impl<T> FromEnvVar for Vec<T>
 where
-    T: From<String> + core::fmt::Debug + 'static,
+    T: TryFrom<String> + core::fmt::Debug + 'static,
+    T::Error: core::error::Error,
+{
+    type Error = T::Error;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        from_env_var_delimited(env_var, ",")
+    }
+}
+
+/// Parses exactly `N` comma-separated elements, e.g. `1,2,3,4` for
+/// `[u64; 4]`. Each element is parsed via [`FromStr`].
+impl<T, const N: usize> FromEnvVar for [T; N]
+where
+    T: FromStr + core::fmt::Debug + 'static,
+    T::Err: core::error::Error + 'static,
 {
-    type Error = Infallible;
+    type Error = ArrayParseError<T::Err>;
 
     fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
         let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
         if s.is_empty() {
-            return Ok(vec![]);
+            return Err(FromEnvErr::empty(env_var));
         }
-        Ok(s.split(',')
-            .map(str::to_string)
-            .map(Into::into)
-            .collect::<Vec<_>>())
+
+        let items: Vec<&str> = s.split(',').collect();
+        if items.len() != N {
+            return Err(FromEnvErr::parse_error(ArrayParseError::Length {
+                expected: N,
+                got: items.len(),
+            }));
+        }
+        let items = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                item.trim()
+                    .parse()
+                    .map_err(|error| ArrayParseError::Element { index, error })
+            })
+            .collect::<Result<Vec<T>, _>>()
+            .map_err(FromEnvErr::parse_error)?;
+        Ok(items
+            .try_into()
+            .expect("element count checked against array length"))
     }
 }
 
+/// Parse the element of a tuple at the given index.
+fn parse_tuple_element<T>(item: &str, index: usize) -> Result<T, FromEnvErr<TupleParseError>>
+where
+    T: FromStr,
+    T::Err: core::error::Error + Send + Sync + 'static,
+{
+    item.trim().parse().map_err(|error| {
+        FromEnvErr::parse_error(TupleParseError::Element {
+            index,
+            error: Box::new(error),
+        })
+    })
+}
+
+macro_rules! impl_for_tuple {
+    ($arity:literal: $($t:ident $index:tt),+) => {
+        /// Parses comma-separated elements positionally, e.g. `10,20` for
+        /// `(u32, u32)`. Each element is parsed via [`FromStr`]. Errors if
+        /// the number of elements differs from the arity.
+        impl<$($t),+> FromEnvVar for ($($t,)+)
+        where
+            $(
+                $t: FromStr + core::fmt::Debug + 'static,
+                $t::Err: core::error::Error + Send + Sync + 'static,
+            )+
+        {
+            type Error = TupleParseError;
+
+            fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+                let s = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
+                if s.is_empty() {
+                    return Err(FromEnvErr::empty(env_var));
+                }
+
+                let items: Vec<&str> = s.split(',').collect();
+                if items.len() != $arity {
+                    return Err(FromEnvErr::parse_error(TupleParseError::Length {
+                        expected: $arity,
+                        got: items.len(),
+                    }));
+                }
+                Ok(($(parse_tuple_element::<$t>(items[$index], $index)?,)+))
+            }
+        }
+    };
+}
+
+impl_for_tuple!(2: A 0, B 1);
+impl_for_tuple!(3: A 0, B 1, C 2);
+impl_for_tuple!(4: A 0, B 1, C 2, D 3);
+
 macro_rules! impl_for_parseable {
     ($($t:ty),*) => {
         $(
@@ -612,7 +1816,8 @@ impl_for_parseable!(
     i128,
     isize,
     url::Url,
-    tracing::Level
+    tracing::Level,
+    tracing::level_filters::LevelFilter
 );
 
 #[cfg(feature = "alloy")]
@@ -631,12 +1836,18 @@ impl<const N: usize> FromEnvVar for alloy::primitives::FixedBytes<N> {
     }
 }
 
+/// Accepts `true`/`false`, `1`/`0`, `yes`/`no` and `on`/`off`,
+/// case-insensitively. An empty value is `false`, as if the flag were unset.
 impl FromEnvVar for bool {
-    type Error = std::str::ParseBoolError;
+    type Error = BoolParseError;
 
     fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
         let s: String = std::env::var(env_var).map_err(|e| FromEnvErr::env_err(env_var, e))?;
-        Ok(!s.is_empty())
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "false" | "0" | "no" | "off" => Ok(false),
+            "true" | "1" | "yes" | "on" => Ok(true),
+            _ => Err(BoolParseError(s).into()),
+        }
     }
 }
 
@@ -682,6 +1893,37 @@ mod test {
         load_expect_err::<T>(env, err);
     }
 
+    #[test]
+    fn test_report() {
+        let inventory = vec![&EnvItemInfo {
+            var: "REPORT_PORT",
+            description: "The port to listen on",
+            optional: false,
+            secret: false,
+            aliases: &[],
+        }];
+
+        unsafe {
+            std::env::remove_var("REPORT_PORT");
+        }
+        let err = u16::from_env_var("REPORT_PORT").unwrap_err();
+        assert_eq!(
+            err.report(&inventory).to_string(),
+            "cannot read environment variable\n  \
+             variable: REPORT_PORT\n  \
+             description: The port to listen on\n  \
+             cause: environment variable not found"
+        );
+
+        set("REPORT_PORT", &"lots");
+        let err = u16::from_env_var("REPORT_PORT").unwrap_err();
+        assert_eq!(
+            err.report(&inventory).to_string(),
+            "failed to parse environment variable\n  \
+             cause: invalid digit found in string"
+        );
+    }
+
     #[test]
     fn test_primitives() {
         test("U8", 42u8);
@@ -700,6 +1942,104 @@ mod test {
         test("String", "hello".to_string());
         test("Url", url::Url::parse("http://example.com").unwrap());
         test("Level", tracing::Level::INFO);
+        test("LevelFilter", tracing::level_filters::LevelFilter::DEBUG);
+    }
+
+    #[test]
+    fn test_level_filter_off() {
+        use tracing::level_filters::LevelFilter;
+
+        set("LEVEL_FILTER_OFF", &"OFF");
+        assert_eq!(
+            LevelFilter::from_env_var("LEVEL_FILTER_OFF").unwrap(),
+            LevelFilter::OFF
+        );
+        assert_eq!(
+            LevelFilter::OFF.to_string().parse::<LevelFilter>().unwrap(),
+            LevelFilter::OFF
+        );
+    }
+
+    #[test]
+    fn test_vec_try_from() {
+        #[derive(Debug, PartialEq)]
+        struct Port(u16);
+
+        impl TryFrom<String> for Port {
+            type Error = ParseIntError;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                s.parse().map(Self)
+            }
+        }
+
+        set("VEC_PORTS", &"80,443");
+        assert_eq!(
+            Vec::<Port>::from_env_var("VEC_PORTS").unwrap(),
+            [Port(80), Port(443)]
+        );
+        set("VEC_PORTS_INVALID", &"80,https");
+        assert!(matches!(
+            Vec::<Port>::from_env_var("VEC_PORTS_INVALID"),
+            Err(FromEnvErr::ParseError(_))
+        ));
+        set("VEC_PORTS_EMPTY", &"");
+        assert!(
+            Vec::<Port>::from_env_var("VEC_PORTS_EMPTY")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_vec_from_string_infallible() {
+        // Elements used to be loaded via `From<String>`, with `Infallible` as
+        // the error. Code relying on that still compiles.
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+
+        impl From<String> for Name {
+            fn from(s: String) -> Self {
+                Self(s)
+            }
+        }
+
+        fn load<T: FromEnvVar<Error = Infallible>>(env: &str) -> T {
+            T::from_env_var(env).unwrap()
+        }
+
+        set("VEC_STRINGS", &"a,b");
+        let strings: Vec<String> = load("VEC_STRINGS");
+        assert_eq!(strings, ["a", "b"]);
+        let names: Vec<Name> = load("VEC_STRINGS");
+        assert_eq!(names, [Name("a".to_owned()), Name("b".to_owned())]);
+
+        let missing: Result<Vec<String>, FromEnvErr<ParseIntError>> =
+            Vec::<String>::from_env_var("VEC_STRINGS_MISSING").map_err(FromEnvErr::infallible_into);
+        assert!(matches!(missing, Err(FromEnvErr::EnvError(..))));
+    }
+
+    #[test]
+    fn test_human_duration() {
+        for (s, expected) in [
+            ("250", Duration::from_millis(250)),
+            ("250ms", Duration::from_millis(250)),
+            ("30s", Duration::from_secs(30)),
+            (" 5 m ", Duration::from_secs(300)),
+            ("2h", Duration::from_secs(7200)),
+            ("1d", Duration::from_secs(86400)),
+        ] {
+            assert_eq!(s.parse::<HumanDuration>().unwrap().0, expected, "{s}");
+        }
+        for s in ["", "s", "-1s", "1.5s", "5 minutes", "99999999999999999999d"] {
+            assert!(s.parse::<HumanDuration>().is_err(), "{s}");
+        }
+
+        set("HUMAN_DURATION", &"10s");
+        assert_eq!(
+            HumanDuration::from_env_var("HUMAN_DURATION").unwrap().0,
+            Duration::from_secs(10)
+        );
     }
 
     #[test]
@@ -713,6 +2053,30 @@ mod test {
         assert_eq!(res, val);
     }
 
+    #[test]
+    fn test_system_time() {
+        use std::time::SystemTime;
+
+        set("SYSTEM_TIME", &1_700_000_000);
+        let res = SystemTime::from_env_var("SYSTEM_TIME").unwrap();
+        assert_eq!(
+            res,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+
+        test_expect_err::<SystemTime, _>(
+            "SYSTEM_TIME_NEGATIVE",
+            -5,
+            FromEnvErr::parse_error("-5".parse::<u64>().unwrap_err().into()),
+        );
+
+        test_expect_err::<SystemTime, _>(
+            "SYSTEM_TIME_OVERFLOW",
+            u64::MAX,
+            FromEnvErr::parse_error(SystemTimeParseError::Overflow(u64::MAX)),
+        );
+    }
+
     #[test]
     fn test_a_few_errors() {
         test_expect_err::<u8, _>(
@@ -723,4 +2087,320 @@ mod test {
 
         test_expect_err::<u8, _>("U8_", "", FromEnvErr::empty("U8_"));
     }
+
+    #[test]
+    fn test_bool() {
+        test("BOOL_TRUE", true);
+        test("BOOL_FALSE", false);
+
+        for (value, expected) in [
+            ("1", true),
+            ("YES", true),
+            ("On", true),
+            ("0", false),
+            ("no", false),
+            ("off", false),
+            ("", false),
+        ] {
+            set("BOOL", &value);
+            assert_eq!(bool::from_env_var("BOOL").unwrap(), expected, "{value}");
+        }
+
+        test_expect_err::<bool, _>(
+            "BOOL_INVALID",
+            "maybe",
+            FromEnvErr::parse_error(BoolParseError("maybe".to_owned())),
+        );
+    }
+
+    #[test]
+    fn test_warn_unknown() {
+        const INVENTORY: &[&EnvItemInfo] = &[&EnvItemInfo {
+            var: "UNKNOWN_TEST_ENDPOINT",
+            description: "An endpoint",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        }];
+        set("UNKNOWN_TEST_ENDPOINT", &"http://localhost");
+        set("UNKNOWN_TEST_ENDPONT", &"http://localhost");
+        set("UNKNOWN_TEST_LEVEL", &"debug");
+        set("UNKNOWN_OTHER_LEVEL", &"debug");
+        assert_eq!(
+            warn_unknown_vars("UNKNOWN_TEST_", INVENTORY),
+            ["UNKNOWN_TEST_ENDPONT", "UNKNOWN_TEST_LEVEL"]
+        );
+        assert!(warn_unknown_vars("UNKNOWN_NONE_", INVENTORY).is_empty());
+    }
+
+    #[test]
+    fn test_effective_vars() {
+        const INVENTORY: &[&EnvItemInfo] = &[
+            &EnvItemInfo {
+                var: "EFFECTIVE_PORT",
+                description: "A port",
+                optional: false,
+                secret: false,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: "EFFECTIVE_PASS",
+                description: "A password",
+                optional: true,
+                secret: true,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: "EFFECTIVE_UNSET",
+                description: "Unset",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+        ];
+        set("EFFECTIVE_PORT", &"8080");
+        set("EFFECTIVE_PASS", &"hunter2");
+        let vars = effective_vars(INVENTORY);
+        assert_eq!(
+            vars.into_iter().collect::<Vec<_>>(),
+            [
+                ("EFFECTIVE_PASS", "[REDACTED]".to_owned()),
+                ("EFFECTIVE_PORT", "8080".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redacted() {
+        set("REDACTED_TOKEN", &"hunter2");
+        let token = Redacted::<String>::from_env_var("REDACTED_TOKEN").unwrap();
+        assert_eq!(token.expose(), "hunter2");
+        assert_eq!(format!("{token:?}"), "[REDACTED]");
+        assert_eq!(format!("{:?}", Some(&token)), "Some([REDACTED])");
+
+        set("REDACTED_PORT", &"nope");
+        assert!(matches!(
+            Redacted::<u16>::from_env_var("REDACTED_PORT"),
+            Err(FromEnvErr::ParseError(_))
+        ));
+        assert!(matches!(
+            Redacted::<String>::from_env_var("REDACTED_MISSING"),
+            Err(FromEnvErr::EnvError(..))
+        ));
+    }
+
+    #[test]
+    fn test_string_empty() {
+        test_expect_err::<String, _>("String_EMPTY", "", FromEnvErr::empty("String_EMPTY"));
+
+        unsafe { std::env::remove_var("String_MISSING") };
+        load_expect_err::<String>(
+            "String_MISSING",
+            FromEnvErr::env_err("String_MISSING", VarError::NotPresent),
+        );
+    }
+
+    #[test]
+    fn test_typed_map() {
+        use std::collections::HashMap;
+
+        set("LIMITS", &"api=100, web = 50");
+        let res = HashMap::<String, u32>::from_env_var("LIMITS").unwrap();
+        assert_eq!(
+            res,
+            HashMap::from([("api".to_string(), 100), ("web".to_string(), 50)])
+        );
+
+        set("LIMITS_EMPTY", &"");
+        let res = HashMap::<String, u32>::from_env_var("LIMITS_EMPTY").unwrap();
+        assert!(res.is_empty());
+
+        test_expect_err::<HashMap<String, u32>, _>(
+            "LIMITS_BAD",
+            "api=100,web=lots",
+            FromEnvErr::parse_error(MapParseError::Value {
+                key: "web".to_string(),
+                error: "lots".parse::<u32>().unwrap_err(),
+            }),
+        );
+
+        test_expect_err::<HashMap<String, u32>, _>(
+            "LIMITS_BAD",
+            "api=100,web",
+            FromEnvErr::parse_error(MapParseError::MissingSeparator("web".to_string())),
+        );
+
+        test_expect_err::<HashMap<String, u32>, _>(
+            "LIMITS_BAD",
+            "api=100,web=50,api=10",
+            FromEnvErr::parse_error(MapParseError::DuplicateKey("api".to_string())),
+        );
+    }
+
+    #[test]
+    fn test_ordered_map() {
+        use std::collections::BTreeMap;
+
+        set("SHARDS", &"3=c, 1 = a,2=b");
+        let res = BTreeMap::<u8, String>::from_env_var("SHARDS").unwrap();
+        assert_eq!(
+            res.into_iter().collect::<Vec<_>>(),
+            [
+                (1, "a".to_string()),
+                (2, "b".to_string()),
+                (3, "c".to_string())
+            ]
+        );
+
+        set("SHARDS_EMPTY", &"");
+        let res = BTreeMap::<u8, String>::from_env_var("SHARDS_EMPTY").unwrap();
+        assert!(res.is_empty());
+
+        test_expect_err::<BTreeMap<u8, String>, _>(
+            "SHARDS_BAD",
+            "1=a,2=b,1=c",
+            FromEnvErr::parse_error(MapParseError::DuplicateKey("1".to_string())),
+        );
+
+        test_expect_err::<BTreeMap<u8, String>, _>(
+            "SHARDS_BAD",
+            "1=a,first=b",
+            FromEnvErr::parse_error(MapParseError::Key {
+                key: "first".to_string(),
+                error: "first".parse::<u8>().unwrap_err(),
+            }),
+        );
+
+        test_expect_err::<BTreeMap<String, u8>, _>(
+            "SHARDS_BAD",
+            "a=1,b=two",
+            FromEnvErr::parse_error(MapParseError::Value {
+                key: "b".to_string(),
+                error: "two".parse::<u8>().unwrap_err(),
+            }),
+        );
+    }
+
+    #[test]
+    fn test_array() {
+        set("RING", &"1, 2,3 ,4");
+        assert_eq!(<[u64; 4]>::from_env_var("RING").unwrap(), [1, 2, 3, 4]);
+
+        test_expect_err::<[u64; 4], _>(
+            "RING_SHORT",
+            "1,2,3",
+            FromEnvErr::parse_error(ArrayParseError::Length {
+                expected: 4,
+                got: 3,
+            }),
+        );
+        test_expect_err::<[u64; 2], _>(
+            "RING_LONG",
+            "1,2,3",
+            FromEnvErr::parse_error(ArrayParseError::Length {
+                expected: 2,
+                got: 3,
+            }),
+        );
+        test_expect_err::<[u64; 2], _>(
+            "RING_BAD",
+            "1,two",
+            FromEnvErr::parse_error(ArrayParseError::Element {
+                index: 1,
+                error: "two".parse::<u64>().unwrap_err(),
+            }),
+        );
+        test_expect_err::<[u64; 2], _>("RING_EMPTY", "", FromEnvErr::empty("RING_EMPTY"));
+
+        let err = ArrayParseError::<ParseIntError>::Length {
+            expected: 4,
+            got: 3,
+        };
+        assert_eq!(
+            err.to_string(),
+            "wrong number of array elements, expected 4, got 3"
+        );
+    }
+
+    #[test]
+    fn test_tuple() {
+        set("RANGE", &"10, 20");
+        assert_eq!(<(u32, u32)>::from_env_var("RANGE").unwrap(), (10, 20));
+
+        set("ENDPOINT", &"localhost,8080,true,info");
+        assert_eq!(
+            <(String, u16, bool, tracing::Level)>::from_env_var("ENDPOINT").unwrap(),
+            ("localhost".to_string(), 8080, true, tracing::Level::INFO)
+        );
+
+        set("RANGE_SHORT", &"10");
+        let err = <(u32, u32)>::from_env_var("RANGE_SHORT").unwrap_err();
+        assert!(matches!(
+            err,
+            FromEnvErr::ParseError(TupleParseError::Length {
+                expected: 2,
+                got: 1
+            })
+        ));
+
+        set("RANGE_BAD", &"10,twenty,30");
+        let err = <(u32, u32, u32)>::from_env_var("RANGE_BAD").unwrap_err();
+        let FromEnvErr::ParseError(err) = err else {
+            panic!("unexpected error {err}");
+        };
+        assert_eq!(
+            err.to_string(),
+            "invalid tuple element at index 1: invalid digit found in string"
+        );
+
+        set("RANGE_EMPTY", &"");
+        assert!(matches!(
+            <(u32, u32)>::from_env_var("RANGE_EMPTY"),
+            Err(FromEnvErr::Empty(_))
+        ));
+    }
+
+    #[test]
+    fn test_range() {
+        use std::ops::{
+            Range,
+            RangeInclusive,
+        };
+
+        set("PORT_RANGE", &"8000..8100");
+        assert_eq!(
+            Range::<u16>::from_env_var("PORT_RANGE").unwrap(),
+            8000..8100
+        );
+
+        set("PORT_RANGE_INCLUSIVE", &"8000 ..= 8100");
+        assert_eq!(
+            RangeInclusive::<u16>::from_env_var("PORT_RANGE_INCLUSIVE").unwrap(),
+            8000..=8100
+        );
+
+        test_expect_err::<Range<u16>, _>(
+            "PORT_RANGE_BAD",
+            "8000..=8100",
+            FromEnvErr::parse_error(RangeParseError::MissingSeparator {
+                separator: "..",
+                range: "8000..=8100".to_string(),
+            }),
+        );
+        test_expect_err::<RangeInclusive<u16>, _>(
+            "PORT_RANGE_BAD",
+            "..=8100",
+            FromEnvErr::parse_error(RangeParseError::MissingBound("..=8100".to_string())),
+        );
+        test_expect_err::<Range<u16>, _>(
+            "PORT_RANGE_BAD",
+            "8100..8000",
+            FromEnvErr::parse_error(RangeParseError::Inverted("8100..8000".to_string())),
+        );
+        test_expect_err::<Range<u16>, _>(
+            "PORT_RANGE_BAD",
+            "8000..lots",
+            FromEnvErr::parse_error(RangeParseError::Bound("lots".parse::<u16>().unwrap_err())),
+        );
+    }
 }
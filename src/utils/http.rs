@@ -0,0 +1,284 @@
+use http::{
+    HeaderMap,
+    HeaderName,
+    HeaderValue,
+    Request,
+    Response,
+};
+use opentelemetry::{
+    Context as OtelContext,
+    propagation::{
+        Extractor,
+        Injector,
+    },
+};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{
+        Context,
+        Poll,
+    },
+};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{
+    Span,
+    field::Empty,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Reads propagation headers from a [`HeaderMap`].
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Writes propagation headers into a [`HeaderMap`].
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) else {
+            return;
+        };
+        self.0.insert(name, value);
+    }
+}
+
+/// Extract the trace context propagated by the caller from the request
+/// headers, using the global text map propagator.
+///
+/// The propagator is installed by [`OtelConfig::provider`]. If none is
+/// installed, the returned context is empty.
+///
+/// [`OtelConfig::provider`]: crate::utils::otlp::OtelConfig::provider
+pub fn extract_context(headers: &HeaderMap) -> OtelContext {
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    })
+}
+
+/// Inject the trace context of the span into the headers of an outgoing
+/// request, using the global text map propagator.
+pub fn inject_context(span: &Span, headers: &mut HeaderMap) {
+    let cx = span.context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(headers))
+    });
+}
+
+/// A [`tower`] [`Layer`] creating a span per HTTP request.
+///
+/// The `http request` span records the `http.request.method` and `url.path`
+/// of the request, and the `http.response.status_code` of the response. Its
+/// parent is the trace context propagated by the caller, as extracted by
+/// [`extract_context`]. Server errors and inner service errors mark the span
+/// as failed.
+///
+/// ```no_run
+/// # use rust_tracing::utils::http::TraceLayer;
+/// let app: axum::Router = axum::Router::new()
+///     .route("/", axum::routing::get(|| async { "hello" }))
+///     .layer(TraceLayer);
+/// ```
+///
+/// [`tower`]: https://docs.rs/tower
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceLayer;
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = TraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceService { inner }
+    }
+}
+
+/// Service instrumenting requests to the inner service. See [`TraceLayer`].
+#[derive(Debug, Clone, Copy)]
+pub struct TraceService<S> {
+    inner: S,
+}
+
+impl<S> TraceService<S> {
+    /// Wrap a service.
+    pub const fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+/// Create the span for a request.
+fn request_span<B>(req: &Request<B>) -> Span {
+    let method = req.method().as_str();
+    let span = tracing::info_span!(
+        "http request",
+        otel.name = method,
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = method,
+        url.path = req.uri().path(),
+        http.response.status_code = Empty,
+    );
+    span.set_parent(extract_context(req.headers()));
+    span
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TraceService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let span = request_span(&req);
+        let inner = span.in_scope(|| self.inner.call(req));
+        ResponseFuture { inner, span }
+    }
+}
+
+pin_project! {
+    /// Response future of [`TraceService`], recording the response status on
+    /// the request span.
+    #[derive(Debug)]
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        span: Span,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _enter = this.span.enter();
+        let res = std::task::ready!(this.inner.poll(cx));
+        match &res {
+            Ok(response) => {
+                let status = response.status();
+                this.span
+                    .record("http.response.status_code", i64::from(status.as_u16()));
+                if status.is_server_error() {
+                    this.span.record("otel.status_code", "error");
+                }
+            }
+            Err(_) => {
+                this.span.record("otel.status_code", "error");
+            }
+        }
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{
+        Router,
+        body::Body,
+        http::StatusCode,
+        routing::get,
+    };
+    use opentelemetry::{
+        Value,
+        trace::{
+            Status,
+            TraceContextExt,
+            TracerProvider,
+        },
+    };
+    use opentelemetry_sdk::{
+        propagation::TraceContextPropagator,
+        trace::{
+            InMemorySpanExporter,
+            SdkTracerProvider,
+        },
+    };
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn request_span() {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut app = Router::new()
+            .route("/users/{id}", get(|| async { "ok" }))
+            .route(
+                "/fail",
+                get(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "fail") }),
+            )
+            .layer(TraceLayer);
+
+        let req = Request::get("/users/1")
+            .header("traceparent", TRACEPARENT)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let req = Request::get("/fail").body(Body::empty()).unwrap();
+        app.call(req).await.unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let attr = |i: usize, key: &str| {
+            spans[i]
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+
+        assert_eq!(spans[0].name, "GET");
+        assert_eq!(spans[0].span_context.trace_id().to_string(), TRACE_ID);
+        assert_eq!(attr(0, "http.request.method"), Some(Value::from("GET")));
+        assert_eq!(attr(0, "url.path"), Some(Value::from("/users/1")));
+        assert_eq!(attr(0, "http.response.status_code"), Some(Value::I64(200)));
+        assert_eq!(spans[0].status, Status::Unset);
+
+        assert_ne!(spans[1].span_context.trace_id().to_string(), TRACE_ID);
+        assert_eq!(attr(1, "http.response.status_code"), Some(Value::I64(500)));
+        assert!(matches!(spans[1].status, Status::Error { .. }));
+
+        let mut headers = HeaderMap::new();
+        let span = tracing::info_span!("outgoing");
+        span.set_parent(extract_context(
+            &[("traceparent".parse().unwrap(), TRACEPARENT.parse().unwrap())]
+                .into_iter()
+                .collect(),
+        ));
+        inject_context(&span, &mut headers);
+        let cx = extract_context(&headers);
+        assert_eq!(cx.span().span_context().trace_id().to_string(), TRACE_ID);
+    }
+}
@@ -1,46 +1,233 @@
+#[cfg(feature = "metrics-server")]
+use crate::utils::from_env::Redacted;
 use crate::utils::from_env::{
     FromEnv,
     FromEnvErr,
     FromEnvVar,
+    HumanDuration,
+};
+use arc_swap::ArcSwap;
+use metrics::{
+    Counter,
+    Gauge,
+    Histogram,
+    HistogramFn,
+    Key,
+    KeyName,
+    Label,
+    Metadata,
+    Recorder,
+    SharedString,
+    Unit,
+};
+use metrics_exporter_prometheus::{
+    BuildError,
+    PrometheusBuilder,
+    PrometheusHandle,
+};
+use metrics_util::MetricKindMask;
+#[cfg(any(feature = "otlp", feature = "json"))]
+use metrics_util::layers::FanoutBuilder;
+#[cfg(all(feature = "metrics-server", unix))]
+use std::path::PathBuf;
+#[cfg(not(feature = "metrics-server"))]
+use std::sync::mpsc;
+use std::{
+    collections::HashMap,
+    num::NonZeroU64,
+    sync::{
+        Arc,
+        Mutex,
+        OnceLock,
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+    },
+    time::Duration,
 };
-use metrics_exporter_prometheus::PrometheusBuilder;
 
-use super::from_env::EnvItemInfo;
+#[cfg(feature = "metrics-server")]
+use super::metrics_server;
+#[cfg(feature = "otlp")]
+use super::otlp_metrics::OtelRecorder;
+use super::{
+    from_env::EnvItemInfo,
+    process_metrics::Collector,
+};
 
 /// Metrics port env var
-const TRACING_METRICS_PORT: &str = "TRACING_METRICS_PORT";
+pub(crate) const TRACING_METRICS_PORT: &str = "TRACING_METRICS_PORT";
+/// Histogram sampling rates env var
+const TRACING_METRICS_SAMPLE: &str = "TRACING_METRICS_SAMPLE";
+/// Metrics enabled env var
+const TRACING_METRICS_ENABLED: &str = "TRACING_METRICS_ENABLED";
+/// Metrics basic auth user env var
+#[cfg(feature = "metrics-server")]
+pub(crate) const TRACING_METRICS_USER: &str = "TRACING_METRICS_USER";
+/// Metrics basic auth password env var
+#[cfg(feature = "metrics-server")]
+pub(crate) const TRACING_METRICS_PASS: &str = "TRACING_METRICS_PASS";
+/// Metrics path env var
+#[cfg(feature = "metrics-server")]
+pub(crate) const TRACING_METRICS_PATH: &str = "TRACING_METRICS_PATH";
+/// Process metrics env var
+const TRACING_PROCESS_METRICS: &str = "TRACING_PROCESS_METRICS";
+/// Metrics Unix domain socket env var
+#[cfg(all(feature = "metrics-server", unix))]
+pub(crate) const TRACING_METRICS_UDS_PATH: &str = "TRACING_METRICS_UDS_PATH";
+/// Log level admin endpoint env var
+#[cfg(feature = "metrics-server")]
+pub(crate) const TRACING_ADMIN_ENABLED: &str = "TRACING_ADMIN_ENABLED";
+/// Idle metric timeout env var
+const TRACING_METRICS_IDLE_TIMEOUT: &str = "TRACING_METRICS_IDLE_TIMEOUT";
+/// Idle metric kinds env var
+const TRACING_METRICS_IDLE_KINDS: &str = "TRACING_METRICS_IDLE_KINDS";
+/// JSON metrics env var
+#[cfg(feature = "json")]
+const TRACING_METRICS_JSON: &str = "TRACING_METRICS_JSON";
+
+/// Path metrics are served at if `TRACING_METRICS_PATH` is unset.
+#[cfg(feature = "metrics-server")]
+pub(crate) const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+/// Maximum number of histogram keys [`SamplingRecorder`] shares observation
+/// counts across handles for.
+const MAX_SAMPLED_KEYS: usize = 10_000;
+
+/// Interval at which histograms are drained, as in
+/// [`PrometheusBuilder::upkeep_timeout`].
+pub(crate) const UPKEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// Prometheus metrics configuration struct.
 ///
 /// Uses the following environment variables:
 /// - `TRACING_METRICS_PORT` - optional. Defaults to 9000 if missing or unparseable.
 ///   The port to bind the metrics server to.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+/// - `TRACING_METRICS_ENABLED` - optional. Defaults to `true`. If `false`, no
+///   exporter is started.
+/// - `TRACING_METRICS_SAMPLE` - optional. Comma-separated `name=N` pairs.
+///   Histograms with a listed name record only 1 in N observations.
+/// - `TRACING_PROCESS_METRICS` - optional. Defaults to `false`. If `true`,
+///   process and tokio runtime stats are recorded periodically.
+/// - `TRACING_METRICS_IDLE_TIMEOUT` - optional. A duration, e.g. `10m`, see
+///   [`HumanDuration`]. If set, metrics not updated for this long are removed
+///   until they are updated again. Defaults to no timeout.
+/// - `TRACING_METRICS_IDLE_KINDS` - optional. Comma-separated metric kinds
+///   the idle timeout applies to, from `counter`, `gauge` and `histogram`.
+///   Defaults to all of them.
+/// - `TRACING_METRICS_JSON` - optional, with the `json` feature. Defaults to
+///   `false`. If `true`, metrics are also kept for `dump_metrics_json`, up to
+///   a bounded number.
+/// - `TRACING_METRICS_PATH` - optional, with the `metrics-server` feature.
+///   Defaults to `/metrics`. The HTTP path metrics are served at.
+/// - `TRACING_METRICS_USER`, `TRACING_METRICS_PASS` - optional, with the
+///   `metrics-server` feature. If either is set, scrapes must authenticate
+///   with these HTTP basic auth credentials.
+/// - `TRACING_ADMIN_ENABLED` - optional, with the `metrics-server` feature.
+///   Defaults to `false`. If `true`, the log filter may be replaced at
+///   runtime via `PUT /log-level`, which requires `TRACING_METRICS_USER` and
+///   `TRACING_METRICS_PASS`.
+/// - `TRACING_METRICS_UDS_PATH` - optional, with the `metrics-server` feature,
+///   Unix only. If set, metrics are served on a Unix domain socket at this
+///   path rather than on the port.
+///
+/// Invalid values are replaced by the defaults, and printed unless they are
+/// booleans. Only an invalid port is reported by [`FromEnv::from_env_all`].
+///
+/// The fields may also be set programmatically, e.g. via
+/// [`TracingBuilder::metrics`], starting from [`MetricsConfig::default`] or
+/// from the environment.
+///
+/// [`TracingBuilder::metrics`]: crate::TracingBuilder::metrics
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
 #[non_exhaustive]
 #[serde(from = "Option<u16>")]
 pub struct MetricsConfig {
     /// `TRACING_METRICS_PORT` - The port on which to bind the metrics server. Defaults
-    /// to `9000` if missing or unparseable.
+    /// to `9000` if missing or unparseable. If `0`, a free port is chosen, see
+    /// [`try_init_metrics`].
     pub port: u16,
+    /// `TRACING_METRICS_ENABLED` - Whether to start the exporter. If `false`,
+    /// no port is bound and no recorder is installed, except to push metrics
+    /// via OTLP when enabled.
+    pub enabled: bool,
+    /// `TRACING_METRICS_SAMPLE` - The sampling rates of histograms by name,
+    /// see [`SamplingRecorder`]. Histograms not in the map record every
+    /// observation.
+    pub sample: HashMap<String, NonZeroU64>,
+    /// `TRACING_PROCESS_METRICS` - Whether to record process and runtime
+    /// stats, see [`init_metrics`].
+    pub process_metrics: bool,
+    /// `TRACING_METRICS_IDLE_TIMEOUT` - How long metrics of the
+    /// [`MetricsConfig::idle_kinds`] may go without updates before they are
+    /// removed, if at all.
+    pub idle_timeout: Option<Duration>,
+    /// `TRACING_METRICS_IDLE_KINDS` - The metric kinds the idle timeout
+    /// applies to.
+    pub idle_kinds: MetricKindMask,
+    /// `TRACING_METRICS_JSON` - Whether to also keep metrics for
+    /// `dump_metrics_json`.
+    #[cfg(feature = "json")]
+    pub json: bool,
+    /// `TRACING_METRICS_PATH` - The HTTP path metrics are served at. Paths not
+    /// starting with `/` are reported, and replaced by `/metrics`.
+    #[cfg(feature = "metrics-server")]
+    pub path: String,
+    /// `TRACING_METRICS_USER` - The basic auth user scrapes must send. If
+    /// only one of the user and password is set, the other is taken to be
+    /// empty.
+    #[cfg(feature = "metrics-server")]
+    pub user: Option<String>,
+    /// `TRACING_METRICS_PASS` - The basic auth password scrapes must send.
+    #[cfg(feature = "metrics-server")]
+    pub pass: Option<Redacted>,
+    /// `TRACING_ADMIN_ENABLED` - Whether to serve `PUT /log-level`.
+    #[cfg(feature = "metrics-server")]
+    pub admin: bool,
+    /// `TRACING_METRICS_UDS_PATH` - The path of a Unix domain socket to serve
+    /// metrics on, taking precedence over the port.
+    #[cfg(all(feature = "metrics-server", unix))]
+    pub uds_path: Option<PathBuf>,
 }
 
 impl Default for MetricsConfig {
     fn default() -> Self {
-        Self { port: 9000 }
+        Self {
+            port: 9000,
+            enabled: true,
+            sample: HashMap::new(),
+            process_metrics: false,
+            idle_timeout: None,
+            idle_kinds: MetricKindMask::ALL,
+            #[cfg(feature = "json")]
+            json: false,
+            #[cfg(feature = "metrics-server")]
+            path: DEFAULT_METRICS_PATH.to_owned(),
+            #[cfg(feature = "metrics-server")]
+            user: None,
+            #[cfg(feature = "metrics-server")]
+            pass: None,
+            #[cfg(feature = "metrics-server")]
+            admin: false,
+            #[cfg(all(feature = "metrics-server", unix))]
+            uds_path: None,
+        }
     }
 }
 
 impl From<Option<u16>> for MetricsConfig {
     fn from(port: Option<u16>) -> Self {
-        Self {
-            port: port.unwrap_or(9000),
-        }
+        port.map(Self::from).unwrap_or_default()
     }
 }
 
 impl From<u16> for MetricsConfig {
     fn from(port: u16) -> Self {
-        Self { port }
+        Self {
+            port,
+            ..Default::default()
+        }
     }
 }
 
@@ -48,38 +235,1037 @@ impl FromEnv for MetricsConfig {
     type Error = std::num::ParseIntError;
 
     fn inventory() -> Vec<&'static EnvItemInfo> {
-        vec![&EnvItemInfo {
-            var: TRACING_METRICS_PORT,
-            description: "Port on which to serve metrics, u16, defaults to 9000",
-            optional: true,
-        }]
+        vec![
+            &EnvItemInfo {
+                var: TRACING_METRICS_PORT,
+                description: "Port on which to serve metrics, u16, defaults to 9000",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: TRACING_METRICS_ENABLED,
+                description: "Whether to serve metrics, boolean, defaults to true",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: TRACING_METRICS_SAMPLE,
+                description: "Comma-separated histogram sampling rates, e.g. `latency=10` to record 1 in 10 observations",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: TRACING_PROCESS_METRICS,
+                description: "Whether to record process and tokio runtime metrics, boolean, defaults to false",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: TRACING_METRICS_IDLE_TIMEOUT,
+                description: "Duration after which metrics that are not updated are removed, e.g. `10m`, defaults to none",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            &EnvItemInfo {
+                var: TRACING_METRICS_IDLE_KINDS,
+                description: "Comma-separated metric kinds the idle timeout applies to, defaults to `counter,gauge,histogram`",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            #[cfg(feature = "json")]
+            &EnvItemInfo {
+                var: TRACING_METRICS_JSON,
+                description: "Whether to keep metrics for `dump_metrics_json`, boolean, defaults to false",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            #[cfg(feature = "metrics-server")]
+            &EnvItemInfo {
+                var: TRACING_METRICS_PATH,
+                description: "HTTP path to serve metrics at, defaults to `/metrics`",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            #[cfg(feature = "metrics-server")]
+            &EnvItemInfo {
+                var: TRACING_METRICS_USER,
+                description: "Username scrapes must authenticate with via HTTP basic auth",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            #[cfg(feature = "metrics-server")]
+            &EnvItemInfo {
+                var: TRACING_METRICS_PASS,
+                description: "Password scrapes must authenticate with via HTTP basic auth",
+                optional: true,
+                secret: true,
+                aliases: &[],
+            },
+            #[cfg(feature = "metrics-server")]
+            &EnvItemInfo {
+                var: TRACING_ADMIN_ENABLED,
+                description: "Whether to serve `PUT /log-level`, replacing the log filter, boolean, defaults to false. Requires TRACING_METRICS_USER and TRACING_METRICS_PASS",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+            #[cfg(all(feature = "metrics-server", unix))]
+            &EnvItemInfo {
+                var: TRACING_METRICS_UDS_PATH,
+                description: "Path of a Unix domain socket to serve metrics on, instead of the port",
+                optional: true,
+                secret: false,
+                aliases: &[],
+            },
+        ]
     }
 
     fn from_env() -> Result<Self, FromEnvErr<Self::Error>> {
-        match u16::from_env_var(TRACING_METRICS_PORT).map(Self::from) {
-            Ok(cfg) => Ok(cfg),
-            Err(_) => Ok(Self::default()),
+        Self::from_env_prefixed("")
+    }
+
+    fn from_env_prefixed(prefix: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        let var = |name: &str| format!("{prefix}{name}");
+        let defaults = Self::default();
+        let flag = |name: &str, default: bool| bool::from_env_var(&var(name)).unwrap_or(default);
+        #[cfg(feature = "metrics-server")]
+        let string = |name: &str| String::from_env_var(&var(name)).ok();
+
+        #[cfg(all(feature = "metrics-server", unix))]
+        let uds_path = string(TRACING_METRICS_UDS_PATH).map(PathBuf::from);
+        #[cfg(all(feature = "metrics-server", unix))]
+        if uds_path.is_some() && string(TRACING_METRICS_PORT).is_some() {
+            eprintln!(
+                "both {TRACING_METRICS_UDS_PATH} and {TRACING_METRICS_PORT} are set, serving metrics on the Unix socket"
+            );
+        }
+
+        Ok(Self {
+            port: u16::from_env_var(&var(TRACING_METRICS_PORT)).unwrap_or(defaults.port),
+            enabled: flag(TRACING_METRICS_ENABLED, defaults.enabled),
+            sample: sample_rates(&var(TRACING_METRICS_SAMPLE)),
+            process_metrics: flag(TRACING_PROCESS_METRICS, defaults.process_metrics),
+            idle_timeout: HumanDuration::from_env_var(&var(TRACING_METRICS_IDLE_TIMEOUT))
+                .inspect_err(|e| {
+                    if matches!(e, FromEnvErr::ParseError(_)) {
+                        eprintln!("{e}")
+                    }
+                })
+                .ok()
+                .map(Duration::from),
+            idle_kinds: idle_kinds(&var(TRACING_METRICS_IDLE_KINDS)),
+            #[cfg(feature = "json")]
+            json: flag(TRACING_METRICS_JSON, defaults.json),
+            #[cfg(feature = "metrics-server")]
+            path: string(TRACING_METRICS_PATH).unwrap_or(defaults.path),
+            #[cfg(feature = "metrics-server")]
+            user: string(TRACING_METRICS_USER),
+            #[cfg(feature = "metrics-server")]
+            pass: string(TRACING_METRICS_PASS).map(Redacted::new),
+            #[cfg(feature = "metrics-server")]
+            admin: flag(TRACING_ADMIN_ENABLED, defaults.admin),
+            #[cfg(all(feature = "metrics-server", unix))]
+            uds_path,
+        })
+    }
+
+    /// Load from env vars, as [`FromEnv::from_env_prefixed`], reporting an
+    /// unparseable port, which loading replaces by the default.
+    fn from_env_all_prefixed(prefix: &str) -> Result<Self, Vec<FromEnvErr<Self::Error>>> {
+        match u16::from_env_var(&format!("{prefix}{TRACING_METRICS_PORT}")) {
+            Err(e @ FromEnvErr::ParseError(_)) => Err(vec![e]),
+            _ => Self::from_env_prefixed(prefix).map_err(|e| vec![e]),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Initialize a [`metrics_exporter_prometheus`] exporter with this
+    /// configuration, as [`try_init_metrics`] does. Returns a guard stopping
+    /// the exporter when dropped, see [`MetricsGuard::detach`] to keep it
+    /// running instead. The handle of the prometheus recorder, e.g. to render
+    /// the metrics text manually, is then available via
+    /// [`prometheus_handle`].
+    ///
+    /// Metric descriptions and units may be registered via the [`metrics`]
+    /// facade, e.g. [`metrics::describe_counter!`], once this returns.
+    ///
+    /// ```no_run
+    /// # use rust_tracing::utils::{from_env::FromEnv, metrics::{self, MetricsConfig}};
+    /// let _guard = MetricsConfig::from_env().unwrap().install().unwrap();
+    /// ::metrics::describe_counter!("requests", ::metrics::Unit::Count, "Requests served");
+    /// if let Some(handle) = metrics::prometheus_handle() {
+    ///     println!("{}", handle.render());
+    /// }
+    /// ```
+    pub fn install(self) -> Result<MetricsGuard, MetricsInitError> {
+        try_init_metrics_with_config(
+            self,
+            #[cfg(feature = "otlp")]
+            None,
+        )
+        .map(|(guard, _)| guard)
+    }
+
+    /// As [`MetricsConfig::install`], additionally forwarding all metrics to
+    /// the given [`OtelRecorder`] if any, e.g. that of
+    /// [`OtelGuard::metrics_recorder`].
+    ///
+    /// [`OtelGuard::metrics_recorder`]: crate::utils::otlp::OtelGuard::metrics_recorder
+    #[cfg(feature = "otlp")]
+    pub fn install_with_otlp(
+        self,
+        otlp: Option<OtelRecorder>,
+    ) -> Result<MetricsGuard, MetricsInitError> {
+        try_init_metrics_with_config(self, otlp).map(|(guard, _)| guard)
+    }
+}
+
+/// Labels installed by [`init_metrics`], if any.
+static GLOBAL_LABELS: OnceLock<GlobalLabels> = OnceLock::new();
+
+/// Handle of the prometheus recorder installed by [`init_metrics`], if any.
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Handle to a set of labels applied to every metric registered through a
+/// [`GlobalLabelsRecorder`]. Cloning the handle shares the labels.
+#[derive(Debug, Clone, Default)]
+pub struct GlobalLabels {
+    labels: Arc<ArcSwap<Vec<Label>>>,
+}
+
+impl GlobalLabels {
+    /// Set a global label, replacing any existing label with the same key.
+    /// Metrics registered after this call carry the new value.
+    pub fn set_global_label(&self, key: impl Into<SharedString>, value: impl Into<SharedString>) {
+        let label = Label::new(key, value);
+        self.labels.rcu(|labels| {
+            let mut labels: Vec<_> = labels
+                .iter()
+                .filter(|l| l.key() != label.key())
+                .cloned()
+                .collect();
+            labels.push(label.clone());
+            labels
+        });
+    }
+
+    /// Remove a global label.
+    pub fn remove_global_label(&self, key: &str) {
+        self.labels.rcu(|labels| {
+            labels
+                .iter()
+                .filter(|l| l.key() != key)
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+    }
+
+    /// Append the current global labels to the key. Labels already present
+    /// on the key take precedence.
+    fn apply(&self, key: &Key) -> Key {
+        let labels = self.labels.load();
+        let extra = labels
+            .iter()
+            .filter(|l| !key.labels().any(|k| k.key() == l.key()))
+            .cloned()
+            .collect();
+        key.with_extra_labels(extra)
+    }
+}
+
+/// A [`Recorder`] wrapper adding reloadable [`GlobalLabels`] to every metric.
+///
+/// Unlike [`PrometheusBuilder::add_global_label`], the labels may be changed
+/// at runtime via [`GlobalLabels::set_global_label`]. Labels are applied when
+/// a metric is registered, so handles registered before a change keep their
+/// previous labels. The `metrics` macros register on each invocation.
+#[derive(Debug)]
+pub struct GlobalLabelsRecorder<R> {
+    inner: R,
+    labels: GlobalLabels,
+}
+
+impl<R> GlobalLabelsRecorder<R> {
+    /// Wrap a recorder, with no global labels set.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            labels: GlobalLabels::default(),
+        }
+    }
+
+    /// Get a handle to the global labels of this recorder.
+    pub fn labels(&self) -> GlobalLabels {
+        self.labels.clone()
+    }
+
+    /// Set a global label. See [`GlobalLabels::set_global_label`].
+    pub fn set_global_label(&self, key: impl Into<SharedString>, value: impl Into<SharedString>) {
+        self.labels.set_global_label(key, value);
+    }
+}
+
+impl<R: Recorder> Recorder for GlobalLabelsRecorder<R> {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_counter(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_gauge(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_histogram(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, metadata: &Metadata<'_>) -> Counter {
+        self.inner
+            .register_counter(&self.labels.apply(key), metadata)
+    }
+
+    fn register_gauge(&self, key: &Key, metadata: &Metadata<'_>) -> Gauge {
+        self.inner.register_gauge(&self.labels.apply(key), metadata)
+    }
+
+    fn register_histogram(&self, key: &Key, metadata: &Metadata<'_>) -> Histogram {
+        self.inner
+            .register_histogram(&self.labels.apply(key), metadata)
+    }
+}
+
+/// Histogram handle recording 1 in `rate` observations into the inner
+/// histogram.
+struct SampledHistogram {
+    inner: Histogram,
+    rate: u64,
+    seen: Arc<AtomicU64>,
+}
+
+impl HistogramFn for SampledHistogram {
+    fn record(&self, value: f64) {
+        if self
+            .seen
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.rate)
+        {
+            self.inner.record(value);
+        }
+    }
+}
+
+/// A [`Recorder`] wrapper recording only 1 in N observations of selected
+/// histograms, to reduce the cost of recording hot metrics.
+///
+/// Sampling is deterministic: the first observation is recorded, then every
+/// Nth one after it. Observations are counted per key, across all handles
+/// registered for it. Beyond 10,000 distinct sampled keys, e.g. due to
+/// high cardinality labels, observations of further keys are counted per
+/// handle instead.
+///
+/// # Accuracy
+///
+/// The recorded count and sum of a sampled histogram are roughly 1/N of the
+/// true values, and are not scaled back up. Quantiles and averages remain
+/// representative as long as observations are not correlated with their
+/// position in the sequence. Rare outliers may be missed entirely.
+#[derive(Debug)]
+pub struct SamplingRecorder<R> {
+    inner: R,
+    rates: HashMap<String, NonZeroU64>,
+    seen: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+}
+
+impl<R> SamplingRecorder<R> {
+    /// Wrap a recorder, sampling histograms by name at the given rates.
+    /// Histograms not in the map record every observation.
+    pub fn new(inner: R, rates: HashMap<String, NonZeroU64>) -> Self {
+        Self {
+            inner,
+            rates,
+            seen: Default::default(),
+        }
+    }
+
+    /// Wrap a recorder, reading the sampling rates from the
+    /// `TRACING_METRICS_SAMPLE` environment variable, e.g. `latency=10` to
+    /// record 1 in 10 observations of `latency`. If the variable is missing
+    /// or unparseable, no histograms are sampled.
+    pub fn from_env(inner: R) -> Self {
+        Self::new(inner, sample_rates(TRACING_METRICS_SAMPLE))
+    }
+}
+
+/// Read histogram sampling rates from the variable, e.g. `latency=10`. If it
+/// is missing or unparseable, no histograms are sampled.
+fn sample_rates(var: &str) -> HashMap<String, NonZeroU64> {
+    match HashMap::from_env_var(var) {
+        Ok(rates) => rates,
+        Err(FromEnvErr::ParseError(e)) => {
+            eprintln!("invalid {var}: {e}, not sampling metrics");
+            HashMap::new()
+        }
+        Err(_) => HashMap::new(),
+    }
+}
+
+impl<R: Recorder> Recorder for SamplingRecorder<R> {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_counter(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_gauge(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_histogram(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, metadata: &Metadata<'_>) -> Counter {
+        self.inner.register_counter(key, metadata)
+    }
+
+    fn register_gauge(&self, key: &Key, metadata: &Metadata<'_>) -> Gauge {
+        self.inner.register_gauge(key, metadata)
+    }
+
+    fn register_histogram(&self, key: &Key, metadata: &Metadata<'_>) -> Histogram {
+        let inner = self.inner.register_histogram(key, metadata);
+        let Some(rate) = self.rates.get(key.name()).filter(|rate| rate.get() > 1) else {
+            return inner;
+        };
+        let seen = {
+            let mut seen = self.seen.lock().unwrap();
+            match seen.get(key) {
+                Some(count) => Arc::clone(count),
+                None if seen.len() < MAX_SAMPLED_KEYS => {
+                    Arc::clone(seen.entry(key.clone()).or_default())
+                }
+                None => Default::default(),
+            }
+        };
+        Histogram::from_arc(Arc::new(SampledHistogram {
+            inner,
+            rate: rate.get(),
+            seen,
+        }))
+    }
+}
+
+/// Set a global label on the recorder installed by [`init_metrics`]. Does
+/// nothing if metrics have not been initialized.
+///
+/// See [`GlobalLabels::set_global_label`].
+pub fn set_global_label(key: impl Into<SharedString>, value: impl Into<SharedString>) {
+    if let Some(labels) = GLOBAL_LABELS.get() {
+        labels.set_global_label(key, value);
+    }
+}
+
+/// Get the handle of the prometheus recorder installed by [`init_metrics`],
+/// e.g. to render the metrics text manually. Returns [`None`] if metrics
+/// have not been initialized, or are disabled via `TRACING_METRICS_ENABLED`.
+pub fn prometheus_handle() -> Option<PrometheusHandle> {
+    PROMETHEUS_HANDLE.get().cloned()
+}
+
+/// Render the metrics recorded by the recorder installed by [`init_metrics`]
+/// in the prometheus text format, as served to scrapes, without going over
+/// HTTP, e.g. for a debug dump or to assert on metrics in tests. Returns
+/// [`None`] if metrics have not been initialized, or are disabled via
+/// `TRACING_METRICS_ENABLED`.
+pub fn render_metrics() -> Option<String> {
+    PROMETHEUS_HANDLE.get().map(PrometheusHandle::render)
+}
+
+/// Guard for the prometheus exporter started by [`init_metrics`]. Dropping it
+/// stops the HTTP listener. The global recorder stays installed, and keeps
+/// recording.
+///
+/// If metrics are disabled via `TRACING_METRICS_ENABLED`, the guard holds no
+/// listener.
+#[derive(Debug)]
+#[must_use = "dropping the guard stops the metrics listener"]
+pub struct MetricsGuard {
+    _exporter: Option<Exporter>,
+    collector: Option<Collector>,
+}
+
+impl MetricsGuard {
+    /// A guard holding no listener.
+    pub(crate) const fn empty() -> Self {
+        Self {
+            _exporter: None,
+            collector: None,
+        }
+    }
+
+    /// Keep the HTTP listener running for the lifetime of the program.
+    pub const fn detach(self) {
+        core::mem::forget(self);
+    }
+}
+
+/// The running exporter, stopped when dropped: the HTTP server, or without
+/// the `metrics-server` feature, the thread draining the histograms.
+#[cfg(feature = "metrics-server")]
+type Exporter = metrics_server::AbortOnDrop;
+#[cfg(not(feature = "metrics-server"))]
+type Exporter = Upkeep;
+
+/// Drains the histograms of the recorder every [`UPKEEP_INTERVAL`] on a
+/// dedicated thread, until dropped.
+#[cfg(not(feature = "metrics-server"))]
+#[derive(Debug)]
+struct Upkeep {
+    _stop: mpsc::Sender<()>,
+}
+
+#[cfg(not(feature = "metrics-server"))]
+impl Upkeep {
+    /// Start draining the histograms of the recorder of the handle.
+    fn spawn(handle: PrometheusHandle) -> Result<Self, BuildError> {
+        let (stop, stopped) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("metrics-upkeep".to_owned())
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stopped.recv_timeout(UPKEEP_INTERVAL)
+                {
+                    handle.run_upkeep();
+                }
+            })
+            .map_err(|e| BuildError::FailedToCreateRuntime(e.to_string()))?;
+        Ok(Self { _stop: stop })
+    }
+}
+
+/// Error returned by [`try_init_metrics`].
+#[derive(Debug)]
+pub enum MetricsInitError {
+    /// The prometheus exporter failed to start, e.g. as the port is in use.
+    Exporter(BuildError),
+    /// A global metrics recorder was already installed.
+    AlreadyInstalled,
+}
+
+impl From<BuildError> for MetricsInitError {
+    fn from(err: BuildError) -> Self {
+        Self::Exporter(err)
+    }
+}
+
+impl core::fmt::Display for MetricsInitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Exporter(err) => write!(f, "failed to start prometheus exporter: {err}"),
+            Self::AlreadyInstalled => f.write_str("a global metrics recorder is already installed"),
         }
     }
 }
 
+impl core::error::Error for MetricsInitError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Exporter(err) => Some(err),
+            Self::AlreadyInstalled => None,
+        }
+    }
+}
+
+/// Wrap the recorder in a [`SamplingRecorder`] at the configured rates and a
+/// [`GlobalLabelsRecorder`] and install it globally. With the `json` feature,
+/// if [`MetricsConfig::json`] is set, metrics are additionally recorded for
+/// `dump_metrics_json`.
+fn install<R: Recorder + Sync + 'static>(
+    recorder: R,
+    cfg: &MetricsConfig,
+) -> Result<(), MetricsInitError> {
+    #[cfg(feature = "json")]
+    let recorder = {
+        let fanout = FanoutBuilder::default().add_recorder(recorder);
+        if cfg.json {
+            fanout.add_recorder(super::metrics_json::installed())
+        } else {
+            fanout
+        }
+        .build()
+    };
+
+    let recorder = GlobalLabelsRecorder::new(SamplingRecorder::new(recorder, cfg.sample.clone()));
+    let labels = recorder.labels();
+    metrics::set_global_recorder(recorder).map_err(|_| MetricsInitError::AlreadyInstalled)?;
+    let _ = GLOBAL_LABELS.set(labels);
+    Ok(())
+}
+
 /// Initialize a [`metrics_exporter_prometheus`] exporter.
 ///
 /// Reads the `TRACING_METRICS_PORT` environment variable to determine the port to bind
 /// the exporter to. If the variable is missing or unparseable, it defaults to
 /// 9000.
 ///
-/// See [`MetricsConfig`] for more information.
+/// Histograms named in the `TRACING_METRICS_SAMPLE` environment variable,
+/// e.g. `latency=10`, record only 1 in N observations. See
+/// [`SamplingRecorder`] for the accuracy tradeoff.
+///
+/// The installed recorder supports reloadable global labels, see
+/// [`set_global_label`]. With the `json` feature, if `TRACING_METRICS_JSON`
+/// is `true`, the recorded metrics may also be rendered as JSON via
+/// `metrics_json::dump_metrics_json`.
+///
+/// If `TRACING_METRICS_ENABLED` is `false`, no port is bound and no recorder
+/// is installed, except to push metrics via OTLP when enabled.
+///
+/// Metrics are served over HTTP with the `metrics-server` feature, enabled
+/// by default. Without it, no port is bound, and [`MetricsConfig`] lacks the
+/// path, credentials, Unix socket and admin endpoint settings below. The metrics may
+/// then be rendered via [`render_metrics`], or exported via OTLP.
+///
+/// Metrics are served at the `TRACING_METRICS_PATH`, `/metrics` by default.
+/// They used to be served at every path, so set it to `/` for scrapers of
+/// the root path. `/healthz` answers `200 OK` without authentication, e.g. for liveness
+/// probes. Other paths are `404 Not Found`.
+///
+/// If `TRACING_PROCESS_METRICS` is set, process stats, e.g. CPU time and
+/// memory, and stats of the tokio runtime this is called on, if any, are
+/// recorded every 5 seconds on a dedicated thread, also if metrics are only
+/// exported via OTLP. See
+/// [`record_process_metrics`].
+///
+/// If `TRACING_METRICS_UDS_PATH` is set, metrics are served on a Unix domain
+/// socket at that path instead of on the port, replacing any socket left
+/// there by a previous run. If `TRACING_METRICS_PORT` is also set, a warning
+/// is printed.
+///
+/// If `TRACING_METRICS_USER` or `TRACING_METRICS_PASS` is set, requests
+/// without matching HTTP basic auth credentials are rejected with
+/// `401 Unauthorized`. The prometheus scrape config must then set
+/// `basic_auth`. A missing user or password is taken to be empty. As the
+/// credentials are sent in the clear, prefer serving metrics on a trusted
+/// network, or behind a TLS-terminating proxy.
+///
+/// If `TRACING_METRICS_IDLE_TIMEOUT` is set, metrics of the
+/// `TRACING_METRICS_IDLE_KINDS` that are not updated for that long are
+/// removed until they are updated again.
+///
+/// If `TRACING_ADMIN_ENABLED` is `true`, `PUT /log-level` replaces the log
+/// filter of [`init_tracing`] with the directives in the request body, in the
+/// same syntax as `RUST_LOG`, via its [`ReloadHandle`]. It answers
+/// `204 No Content` on success, `400 Bad Request` if the directives are
+/// invalid, and `503 Service Unavailable` if tracing is not initialized. It
+/// requires the same credentials as scrapes. If none are configured, it
+/// refuses every request with `403 Forbidden`, and a warning is printed.
+/// E.g.:
+///
+/// ```sh
+/// curl -X PUT -u user:pass -d 'info,my_crate=debug' localhost:9000/log-level
+/// ```
+///
+/// All these settings are loaded via [`MetricsConfig::from_env`]. See
+/// [`MetricsConfig`] for more information, and [`MetricsConfig::install`] to
+/// set them programmatically instead.
 ///
 /// # Panics
 ///
 /// This function will panic if the exporter fails to install, e.g. if the port
-/// is in use.
+/// is in use. See [`try_init_metrics`] for a non-panicking version.
+///
+/// [`record_process_metrics`]: crate::utils::process_metrics::record_process_metrics
+/// [`init_tracing`]: crate::utils::tracing::init_tracing
+/// [`ReloadHandle`]: crate::utils::tracing::ReloadHandle
 pub fn init_metrics() {
-    let cfg = MetricsConfig::from_env().unwrap();
+    init_metrics_with_config(
+        MetricsConfig::from_env().unwrap(),
+        #[cfg(feature = "otlp")]
+        None,
+    )
+    .detach();
+}
+
+/// Initialize a [`metrics_exporter_prometheus`] exporter, returning the port
+/// it listens on, or an error if the exporter fails to start or a global
+/// recorder was already installed. Returns [`None`] if metrics are disabled
+/// via `TRACING_METRICS_ENABLED`, or served on a Unix domain socket.
+///
+/// If `TRACING_METRICS_PORT` is `0`, the exporter listens on a free port
+/// assigned by the OS, e.g. so that parallel tests don't collide.
+///
+/// See [`init_metrics`] for more information.
+pub fn try_init_metrics() -> Result<Option<u16>, MetricsInitError> {
+    let (guard, port) = try_init_metrics_with_config(
+        MetricsConfig::from_env().unwrap(),
+        #[cfg(feature = "otlp")]
+        None,
+    )?;
+    guard.detach();
+    Ok(port)
+}
 
-    PrometheusBuilder::new()
-        .with_http_listener(([0, 0, 0, 0], cfg.port))
-        .install()
-        .expect("failed to install prometheus exporter");
+/// Initialize a [`metrics_exporter_prometheus`] exporter, additionally
+/// forwarding all metrics to the given [`OtelRecorder`] if any.
+///
+/// See [`init_metrics`] for more information.
+///
+/// # Panics
+///
+/// This function will panic if the exporter fails to install, e.g. if the port
+/// is in use.
+#[cfg(feature = "otlp")]
+pub fn init_metrics_with_otlp(otlp: Option<OtelRecorder>) {
+    init_metrics_with_config(MetricsConfig::from_env().unwrap(), otlp).detach();
+}
+
+/// Initialize a [`metrics_exporter_prometheus`] exporter with the given
+/// configuration, additionally forwarding all metrics to the given
+/// [`OtelRecorder`] if any. Returns a guard stopping the exporter when
+/// dropped.
+pub(crate) fn init_metrics_with_config(
+    cfg: MetricsConfig,
+    #[cfg(feature = "otlp")] otlp: Option<OtelRecorder>,
+) -> MetricsGuard {
+    try_init_metrics_with_config(
+        cfg,
+        #[cfg(feature = "otlp")]
+        otlp,
+    )
+    .expect("failed to install prometheus exporter")
+    .0
+}
+
+/// As [`init_metrics_with_config`], returning an error rather than panicking,
+/// and additionally returning the port the exporter listens on. If the
+/// exporter starts but the recorder fails to install, the exporter is
+/// stopped.
+fn try_init_metrics_with_config(
+    cfg: MetricsConfig,
+    #[cfg(feature = "otlp")] otlp: Option<OtelRecorder>,
+) -> Result<(MetricsGuard, Option<u16>), MetricsInitError> {
+    if !cfg.enabled {
+        #[cfg(feature = "otlp")]
+        if let Some(otlp) = otlp {
+            install(otlp, &cfg)?;
+            return Ok((
+                MetricsGuard {
+                    _exporter: None,
+                    collector: spawn_collector(&cfg),
+                },
+                None,
+            ));
+        }
+        return Ok((MetricsGuard::empty(), None));
+    }
+
+    let prometheus = prometheus_builder(&cfg).build_recorder();
+    let handle = prometheus.handle();
+    #[cfg(feature = "metrics-server")]
+    let (exporter, port) = {
+        let listener = metrics_server::bind(&cfg)
+            .map_err(|e| BuildError::FailedToCreateHTTPListener(e.to_string()))?;
+        let port = listener.port();
+        let config = metrics_server::ServerConfig::new(&cfg);
+        (
+            metrics_server::spawn_exporter(listener, handle.clone(), config)?,
+            port,
+        )
+    };
+    // Without the server, the exporter only drains the histograms.
+    #[cfg(not(feature = "metrics-server"))]
+    let (exporter, port) = (Upkeep::spawn(handle.clone())?, None);
+    let mut guard = MetricsGuard {
+        _exporter: Some(exporter),
+        collector: None,
+    };
+
+    #[cfg(feature = "otlp")]
+    if let Some(otlp) = otlp {
+        let fanout = FanoutBuilder::default()
+            .add_recorder(prometheus)
+            .add_recorder(otlp)
+            .build();
+        install(fanout, &cfg)?;
+        let _ = PROMETHEUS_HANDLE.set(handle);
+        guard.collector = spawn_collector(&cfg);
+        return Ok((guard, port));
+    }
+
+    install(prometheus, &cfg)?;
+    let _ = PROMETHEUS_HANDLE.set(handle);
+    guard.collector = spawn_collector(&cfg);
+    Ok((guard, port))
+}
+
+/// Start recording process and runtime stats if
+/// [`MetricsConfig::process_metrics`] is set, with the stats of the tokio
+/// runtime of the caller, if any. Failures to start are printed.
+fn spawn_collector(cfg: &MetricsConfig) -> Option<Collector> {
+    if !cfg.process_metrics {
+        return None;
+    }
+    Collector::spawn(
+        #[cfg(feature = "tokio")]
+        tokio::runtime::Handle::try_current().ok(),
+    )
+    .inspect_err(|e| eprintln!("failed to start recording process metrics: {e}"))
+    .ok()
+}
+
+/// Create the prometheus builder, expiring metrics of the configured idle
+/// kinds after the idle timeout, if any.
+fn prometheus_builder(cfg: &MetricsConfig) -> PrometheusBuilder {
+    PrometheusBuilder::new().idle_timeout(cfg.idle_kinds, cfg.idle_timeout)
+}
+
+/// The metric kinds in the variable, defaulting to all. Unknown kinds are
+/// reported, and ignored.
+fn idle_kinds(var: &str) -> MetricKindMask {
+    let Ok(kinds) = String::from_env_var(var) else {
+        return MetricKindMask::ALL;
+    };
+    kinds
+        .split(',')
+        .filter_map(|kind| {
+            match kind.trim().to_ascii_lowercase().as_str() {
+                "counter" => Some(MetricKindMask::COUNTER),
+                "gauge" => Some(MetricKindMask::GAUGE),
+                "histogram" => Some(MetricKindMask::HISTOGRAM),
+                _ => {
+                    eprintln!("invalid metric kind in {var}: {kind}");
+                    None
+                }
+            }
+        })
+        .fold(MetricKindMask::NONE, |mask, kind| mask | kind)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn load_prefixed() {
+        unsafe { std::env::set_var("TENANT_M_TRACING_METRICS_PORT", "9191") };
+        let cfg = MetricsConfig::from_env_prefixed("TENANT_M_").unwrap();
+        assert_eq!(cfg, MetricsConfig::from(9191));
+        assert!(MetricsConfig::from_env_all_prefixed("TENANT_M_").is_ok());
+
+        unsafe { std::env::set_var("TENANT_M_TRACING_METRICS_PORT", "not-a-port") };
+        assert_eq!(
+            MetricsConfig::from_env_prefixed("TENANT_M_").unwrap(),
+            MetricsConfig::default()
+        );
+        let errors = MetricsConfig::from_env_all_prefixed("TENANT_M_").unwrap_err();
+        assert!(matches!(errors[..], [FromEnvErr::ParseError(_)]));
+        unsafe { std::env::remove_var("TENANT_M_TRACING_METRICS_PORT") };
+    }
+
+    #[cfg(feature = "metrics-server")]
+    #[test]
+    #[serial_test::serial]
+    fn try_init_ephemeral_port() {
+        unsafe { std::env::set_var(TRACING_METRICS_PORT, "0") };
+        let port = try_init_metrics().unwrap().unwrap();
+        assert_ne!(port, 0);
+
+        let listening = || std::net::TcpStream::connect(("127.0.0.1", port)).is_ok();
+        assert!((0..100).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            listening()
+        }));
+
+        let handle = prometheus_handle().unwrap();
+        metrics::describe_counter!("described_total", Unit::Count, "A described counter");
+        metrics::counter!("described_total").increment(3);
+        let rendered = handle.render();
+        assert!(
+            rendered.contains("# HELP described_total A described counter"),
+            "{rendered}"
+        );
+        let rendered = render_metrics().unwrap();
+        assert!(rendered.contains("described_total 3"), "{rendered}");
+
+        assert!(matches!(
+            try_init_metrics(),
+            Err(MetricsInitError::AlreadyInstalled)
+        ));
+        assert!(matches!(
+            MetricsConfig::from(0).install(),
+            Err(MetricsInitError::AlreadyInstalled)
+        ));
+        #[cfg(feature = "otlp")]
+        assert!(matches!(
+            MetricsConfig::from(0).install_with_otlp(None),
+            Err(MetricsInitError::AlreadyInstalled)
+        ));
+
+        unsafe { std::env::set_var(TRACING_METRICS_ENABLED, "false") };
+        assert!(matches!(try_init_metrics(), Ok(None)));
+
+        unsafe {
+            std::env::remove_var(TRACING_METRICS_PORT);
+            std::env::remove_var(TRACING_METRICS_ENABLED);
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn idle_timeout() {
+        unsafe {
+            std::env::set_var(TRACING_METRICS_IDLE_TIMEOUT, "20ms");
+            std::env::set_var(TRACING_METRICS_IDLE_KINDS, "counter, bogus");
+        }
+        let cfg = MetricsConfig::from_env().unwrap();
+        assert_eq!(cfg.idle_kinds, MetricKindMask::COUNTER);
+        assert_eq!(cfg.idle_timeout, Some(Duration::from_millis(20)));
+        let recorder = prometheus_builder(&cfg).build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("idle_counter").increment(1);
+            metrics::gauge!("idle_gauge").set(1.0);
+        });
+        let rendered = handle.render();
+        assert!(rendered.contains("idle_counter 1"), "{rendered}");
+
+        std::thread::sleep(Duration::from_millis(50));
+        let rendered = handle.render();
+        assert!(!rendered.contains("idle_counter"), "{rendered}");
+        assert!(rendered.contains("idle_gauge 1"), "{rendered}");
+
+        unsafe {
+            std::env::remove_var(TRACING_METRICS_IDLE_TIMEOUT);
+            std::env::remove_var(TRACING_METRICS_IDLE_KINDS);
+        }
+        assert_eq!(idle_kinds(TRACING_METRICS_IDLE_KINDS), MetricKindMask::ALL);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn config_from_env_prefixed() {
+        let vars = [
+            (TRACING_METRICS_PORT, "9100"),
+            (TRACING_METRICS_ENABLED, "false"),
+            (TRACING_METRICS_SAMPLE, "latency=10"),
+            (TRACING_PROCESS_METRICS, "true"),
+            (TRACING_METRICS_IDLE_TIMEOUT, "10m"),
+            (TRACING_METRICS_IDLE_KINDS, "gauge"),
+            #[cfg(feature = "metrics-server")]
+            (TRACING_METRICS_PATH, "/prometheus"),
+            #[cfg(feature = "metrics-server")]
+            (TRACING_METRICS_USER, "prometheus"),
+            #[cfg(feature = "metrics-server")]
+            (TRACING_METRICS_PASS, "hunter2"),
+            #[cfg(feature = "metrics-server")]
+            (TRACING_ADMIN_ENABLED, "true"),
+        ];
+        for (var, value) in vars {
+            unsafe { std::env::set_var(format!("APP_{var}"), value) };
+        }
+
+        // Unprefixed vars are not read.
+        assert_eq!(MetricsConfig::from_env().unwrap(), MetricsConfig::default());
+        let cfg = MetricsConfig::from_env_prefixed("APP_").unwrap();
+        assert_eq!(
+            cfg,
+            MetricsConfig {
+                port: 9100,
+                enabled: false,
+                sample: HashMap::from([("latency".to_owned(), NonZeroU64::new(10).unwrap())]),
+                process_metrics: true,
+                idle_timeout: Some(Duration::from_secs(600)),
+                idle_kinds: MetricKindMask::GAUGE,
+                #[cfg(feature = "metrics-server")]
+                path: "/prometheus".to_owned(),
+                #[cfg(feature = "metrics-server")]
+                user: Some("prometheus".to_owned()),
+                #[cfg(feature = "metrics-server")]
+                pass: Some(Redacted::new("hunter2".to_owned())),
+                #[cfg(feature = "metrics-server")]
+                admin: true,
+                ..Default::default()
+            }
+        );
+
+        for (var, _) in vars {
+            unsafe { std::env::remove_var(format!("APP_{var}")) };
+        }
+    }
+
+    #[test]
+    fn global_label_reload() {
+        let prometheus = PrometheusBuilder::new().build_recorder();
+        let handle = prometheus.handle();
+        let recorder = GlobalLabelsRecorder::new(prometheus);
+
+        recorder.set_global_label("role", "follower");
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("elections").increment(1);
+        });
+        let rendered = handle.render();
+        assert!(rendered.contains(r#"elections{role="follower"} 1"#));
+
+        recorder.set_global_label("role", "leader");
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("elections").increment(1);
+        });
+        let rendered = handle.render();
+        assert!(rendered.contains(r#"elections{role="leader"} 1"#));
+
+        recorder.labels().remove_global_label("role");
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("elections", "role" => "candidate").increment(1);
+        });
+        let rendered = handle.render();
+        assert!(rendered.contains(r#"elections{role="candidate"} 1"#));
+    }
+
+    #[test]
+    fn sampled_histogram() {
+        let prometheus = PrometheusBuilder::new().build_recorder();
+        let handle = prometheus.handle();
+        let rates = HashMap::from([("hot".to_string(), NonZeroU64::new(10).unwrap())]);
+        let recorder = SamplingRecorder::new(prometheus, rates);
+
+        metrics::with_local_recorder(&recorder, || {
+            for i in 0..1000 {
+                metrics::histogram!("hot").record(i as f64);
+                metrics::histogram!("cold").record(i as f64);
+            }
+        });
+
+        let rendered = handle.render();
+        let count = |name: &str| -> u64 {
+            rendered
+                .lines()
+                .find_map(|l| l.strip_prefix(&format!("{name}_count ")))
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        let hot = count("hot");
+        assert!((90..=110).contains(&hot), "{rendered}");
+        assert_eq!(count("cold"), 1000);
+    }
+
+    #[test]
+    fn sampled_keys_bounded() {
+        let rates = HashMap::from([("hot".to_string(), NonZeroU64::new(10).unwrap())]);
+        let recorder = SamplingRecorder::new(metrics::NoopRecorder, rates);
+
+        metrics::with_local_recorder(&recorder, || {
+            for i in 0..MAX_SAMPLED_KEYS + 10 {
+                metrics::histogram!("hot", "id" => i.to_string()).record(1.0);
+            }
+        });
+        assert_eq!(recorder.seen.lock().unwrap().len(), MAX_SAMPLED_KEYS);
+    }
 }
@@ -0,0 +1,350 @@
+use metrics::{
+    Counter,
+    Gauge,
+    Histogram,
+    HistogramFn,
+    Key,
+    KeyName,
+    Metadata,
+    Recorder,
+    SharedString,
+    Unit,
+};
+use metrics_util::registry::{
+    Registry,
+    Storage,
+};
+use serde_json::{
+    Map,
+    Value,
+    json,
+};
+use std::sync::{
+    Arc,
+    OnceLock,
+    atomic::{
+        AtomicU64,
+        AtomicUsize,
+        Ordering,
+    },
+};
+
+/// Recorder installed by [`init_metrics`], if any.
+///
+/// [`init_metrics`]: crate::utils::metrics::init_metrics
+static INSTALLED: OnceLock<JsonRecorder> = OnceLock::new();
+
+/// Maximum number of metrics a [`JsonRecorder`] keeps, so that high
+/// cardinality labels cannot grow it without bound.
+pub const MAX_METRICS: usize = 10_000;
+
+/// Summary of the observations of a histogram. Individual observations are
+/// not kept.
+#[derive(Debug)]
+struct HistogramSummary {
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for HistogramSummary {
+    fn default() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0f64.to_bits()),
+            min: AtomicU64::new(f64::INFINITY.to_bits()),
+            max: AtomicU64::new(f64::NEG_INFINITY.to_bits()),
+        }
+    }
+}
+
+/// Atomically apply `f` to the `f64` stored in `atomic`.
+fn update_f64(atomic: &AtomicU64, f: impl Fn(f64) -> f64) {
+    let _ = atomic.fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+        Some(f(f64::from_bits(bits)).to_bits())
+    });
+}
+
+impl HistogramFn for HistogramSummary {
+    fn record(&self, value: f64) {
+        self.count.fetch_add(1, Ordering::AcqRel);
+        update_f64(&self.sum, |sum| sum + value);
+        update_f64(&self.min, |min| min.min(value));
+        update_f64(&self.max, |max| max.max(value));
+    }
+}
+
+impl HistogramSummary {
+    fn to_json(&self) -> Value {
+        let count = self.count.load(Ordering::Acquire);
+        let load = |atomic: &AtomicU64| f64::from_bits(atomic.load(Ordering::Acquire));
+        if count == 0 {
+            return json!({ "count": 0, "sum": 0.0 });
+        }
+        json!({
+            "count": count,
+            "sum": load(&self.sum),
+            "min": load(&self.min),
+            "max": load(&self.max),
+        })
+    }
+}
+
+/// Storage for [`JsonRecorder`], summarizing histograms.
+#[derive(Debug, Clone, Copy)]
+struct SummaryStorage;
+
+impl<K> Storage<K> for SummaryStorage {
+    type Counter = Arc<AtomicU64>;
+    type Gauge = Arc<AtomicU64>;
+    type Histogram = Arc<HistogramSummary>;
+
+    fn counter(&self, _: &K) -> Self::Counter {
+        Default::default()
+    }
+
+    fn gauge(&self, _: &K) -> Self::Gauge {
+        Default::default()
+    }
+
+    fn histogram(&self, _: &K) -> Self::Histogram {
+        Default::default()
+    }
+}
+
+/// A [`Recorder`] that keeps the current value of each metric, so that they
+/// may be rendered as JSON via [`JsonRecorder::to_json`].
+///
+/// Histograms are summarized by their count, sum, min and max.
+///
+/// At most [`MAX_METRICS`] metrics are kept. Further metrics are not
+/// recorded, until the recorder is cleared via [`JsonRecorder::clear`].
+///
+/// If `TRACING_METRICS_JSON` is `true`, [`init_metrics`] installs one
+/// alongside the prometheus exporter. Its metrics are rendered by
+/// [`dump_metrics_json`].
+///
+/// [`init_metrics`]: crate::utils::metrics::init_metrics
+#[derive(Clone)]
+pub struct JsonRecorder {
+    registry: Arc<Registry<Key, SummaryStorage>>,
+    /// Number of metrics registered since the last clear. Concurrent first
+    /// registrations of the same metric may each count it.
+    len: Arc<AtomicUsize>,
+}
+
+impl core::fmt::Debug for JsonRecorder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("JsonRecorder").finish_non_exhaustive()
+    }
+}
+
+impl Default for JsonRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonRecorder {
+    /// Create a new recorder, with no metrics recorded.
+    pub fn new() -> Self {
+        Self {
+            registry: Arc::new(Registry::new(SummaryStorage)),
+            len: Default::default(),
+        }
+    }
+
+    /// Remove all recorded metrics. Handles obtained before keep working,
+    /// but are no longer rendered.
+    pub fn clear(&self) {
+        self.registry.clear();
+        self.len.store(0, Ordering::Release);
+    }
+
+    /// Whether a metric not yet registered may be, without exceeding
+    /// [`MAX_METRICS`]. Counts it if so.
+    fn admit(&self) -> bool {
+        self.len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| {
+                (len < MAX_METRICS).then_some(len + 1)
+            })
+            .is_ok()
+    }
+
+    /// Render the recorded metrics as a JSON array of objects with `name`,
+    /// `type`, `labels` and `value` keys, sorted by name.
+    ///
+    /// ```json
+    /// [{ "name": "requests", "type": "counter", "labels": { "route": "/" }, "value": 5 }]
+    /// ```
+    ///
+    /// The value of a histogram is an object with `count`, `sum`, `min` and
+    /// `max` keys.
+    pub fn to_json(&self) -> Value {
+        let mut metrics = vec![];
+        self.registry.visit_counters(|key, counter| {
+            metrics.push(entry(
+                key,
+                "counter",
+                counter.load(Ordering::Acquire).into(),
+            ));
+        });
+        self.registry.visit_gauges(|key, gauge| {
+            let value = f64::from_bits(gauge.load(Ordering::Acquire));
+            metrics.push(entry(key, "gauge", value.into()));
+        });
+        self.registry.visit_histograms(|key, histogram| {
+            metrics.push(entry(key, "histogram", histogram.to_json()));
+        });
+        metrics.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        Value::Array(metrics)
+    }
+}
+
+/// Render a single metric.
+fn entry(key: &Key, kind: &str, value: Value) -> Value {
+    let labels: Map<_, _> = key
+        .labels()
+        .map(|label| (label.key().to_owned(), label.value().into()))
+        .collect();
+    json!({
+        "name": key.name(),
+        "type": kind,
+        "labels": labels,
+        "value": value,
+    })
+}
+
+impl Recorder for JsonRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        match self.registry.get_counter(key) {
+            Some(counter) => Counter::from_arc(counter),
+            None if self.admit() => {
+                Counter::from_arc(self.registry.get_or_create_counter(key, Arc::clone))
+            }
+            None => Counter::noop(),
+        }
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        match self.registry.get_gauge(key) {
+            Some(gauge) => Gauge::from_arc(gauge),
+            None if self.admit() => {
+                Gauge::from_arc(self.registry.get_or_create_gauge(key, Arc::clone))
+            }
+            None => Gauge::noop(),
+        }
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        match self.registry.get_histogram(key) {
+            Some(histogram) => Histogram::from_arc(histogram),
+            None if self.admit() => {
+                Histogram::from_arc(self.registry.get_or_create_histogram(key, Arc::clone))
+            }
+            None => Histogram::noop(),
+        }
+    }
+}
+
+/// Get the recorder to install globally, creating it on first use.
+pub(crate) fn installed() -> JsonRecorder {
+    INSTALLED.get_or_init(JsonRecorder::new).clone()
+}
+
+/// Render the metrics recorded by the recorder installed by [`init_metrics`]
+/// as JSON. See [`JsonRecorder::to_json`] for the format. Returns an empty
+/// array if metrics have not been initialized, or `TRACING_METRICS_JSON` is
+/// not `true`.
+///
+/// [`init_metrics`]: crate::utils::metrics::init_metrics
+pub fn dump_metrics_json() -> Value {
+    INSTALLED
+        .get()
+        .map(JsonRecorder::to_json)
+        .unwrap_or_else(|| Value::Array(vec![]))
+}
+
+/// Remove all metrics recorded by the recorder installed by [`init_metrics`],
+/// e.g. after dumping them. See [`JsonRecorder::clear`].
+///
+/// [`init_metrics`]: crate::utils::metrics::init_metrics
+pub fn clear_metrics_json() {
+    if let Some(recorder) = INSTALLED.get() {
+        recorder.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_recorded_metrics() {
+        let recorder = JsonRecorder::new();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("requests", "route" => "/").increment(2);
+            metrics::counter!("requests", "route" => "/").increment(3);
+            metrics::gauge!("in_flight").set(7.0);
+            metrics::histogram!("latency").record(1.5);
+            metrics::histogram!("latency").record(0.5);
+        });
+
+        assert_eq!(
+            recorder.to_json(),
+            json!([
+                {
+                    "name": "in_flight",
+                    "type": "gauge",
+                    "labels": {},
+                    "value": 7.0,
+                },
+                {
+                    "name": "latency",
+                    "type": "histogram",
+                    "labels": {},
+                    "value": { "count": 2, "sum": 2.0, "min": 0.5, "max": 1.5 },
+                },
+                {
+                    "name": "requests",
+                    "type": "counter",
+                    "labels": { "route": "/" },
+                    "value": 5,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn bounded() {
+        let recorder = JsonRecorder::new();
+        metrics::with_local_recorder(&recorder, || {
+            for i in 0..MAX_METRICS + 10 {
+                metrics::counter!("requests", "id" => i.to_string()).increment(1);
+            }
+            // Metrics already kept are still updated.
+            metrics::counter!("requests", "id" => "0").increment(1);
+        });
+        let metrics = recorder.to_json();
+        let metrics = metrics.as_array().unwrap();
+        assert_eq!(metrics.len(), MAX_METRICS);
+        assert!(
+            metrics
+                .iter()
+                .any(|m| m["labels"]["id"] == "0" && m["value"] == 2)
+        );
+
+        recorder.clear();
+        assert_eq!(recorder.to_json(), json!([]));
+        metrics::with_local_recorder(&recorder, || metrics::gauge!("in_flight").set(1.0));
+        assert_eq!(recorder.to_json().as_array().unwrap().len(), 1);
+    }
+}
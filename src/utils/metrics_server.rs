@@ -0,0 +1,729 @@
+use crate::utils::{
+    from_env::Redacted,
+    metrics::{
+        DEFAULT_METRICS_PATH,
+        MetricsConfig,
+        TRACING_ADMIN_ENABLED,
+        TRACING_METRICS_PASS,
+        TRACING_METRICS_PATH,
+        TRACING_METRICS_USER,
+        UPKEEP_INTERVAL,
+    },
+    tracing::ReloadHandle,
+};
+use base64::{
+    Engine,
+    engine::general_purpose::STANDARD as BASE64,
+};
+use http_body_util::{
+    BodyExt,
+    Full,
+    Limited,
+};
+use hyper::{
+    Method,
+    Request,
+    Response,
+    StatusCode,
+    body::{
+        Bytes,
+        Incoming,
+    },
+    header::{
+        ALLOW,
+        AUTHORIZATION,
+        CONTENT_TYPE,
+        HeaderValue,
+        WWW_AUTHENTICATE,
+    },
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use metrics_exporter_prometheus::{
+    BuildError,
+    PrometheusHandle,
+};
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{
+        AsyncRead,
+        AsyncWrite,
+    },
+    net::TcpListener,
+    task::AbortHandle,
+};
+
+/// Path answering health checks.
+const HEALTH_PATH: &str = "/healthz";
+/// Path of the admin endpoint replacing the log filter.
+const LOG_LEVEL_PATH: &str = "/log-level";
+/// Maximum size of the filter directives sent to the admin endpoint.
+const MAX_DIRECTIVES_LEN: usize = 4096;
+
+/// A bound listener for the exporter to serve on.
+#[derive(Debug)]
+pub(crate) enum Listener {
+    Tcp(std::net::TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+impl Listener {
+    /// Bind a TCP listener to the address.
+    fn tcp(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::Tcp(listener))
+    }
+
+    /// Bind a Unix domain socket listener at the path, replacing any socket
+    /// left there by a previous run.
+    #[cfg(unix)]
+    fn unix(path: &std::path::Path) -> std::io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::Unix(listener))
+    }
+
+    /// The bound TCP port, if listening on TCP.
+    pub(crate) fn port(&self) -> Option<u16> {
+        match self {
+            Self::Tcp(listener) => listener.local_addr().ok().map(|addr| addr.port()),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+}
+
+/// Bind the exporter's listener, to the Unix domain socket at
+/// [`MetricsConfig::uds_path`] if set, and otherwise to the port on all
+/// interfaces.
+pub(crate) fn bind(cfg: &MetricsConfig) -> std::io::Result<Listener> {
+    #[cfg(unix)]
+    if let Some(path) = &cfg.uds_path {
+        return Listener::unix(path);
+    }
+    Listener::tcp(SocketAddr::from(([0, 0, 0, 0], cfg.port)))
+}
+
+/// Settings of the HTTP server serving metrics.
+#[derive(Debug, Clone)]
+pub(crate) struct ServerConfig {
+    /// The path metrics are served at.
+    path: String,
+    /// The `Authorization` header value scrapes must send, if any.
+    auth: Option<String>,
+    /// Whether to serve the log level admin endpoint.
+    admin: bool,
+    /// The log filter the admin endpoint replaces, that of
+    /// [`crate::utils::tracing::init_tracing`] if [`None`].
+    filter: Option<ReloadHandle>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_METRICS_PATH.to_owned(),
+            auth: None,
+            admin: false,
+            filter: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Take the server settings of the metrics config. Paths not starting
+    /// with `/` are reported, and replaced by the default.
+    pub(crate) fn new(cfg: &MetricsConfig) -> Self {
+        let path = Some(&cfg.path)
+            .filter(|path| {
+                let valid = path.starts_with('/');
+                if !valid {
+                    eprintln!("invalid {TRACING_METRICS_PATH}, must start with `/`: {path}");
+                }
+                valid
+            })
+            .map_or_else(|| DEFAULT_METRICS_PATH.to_owned(), Clone::clone);
+        let auth = basic_auth(cfg.user.as_deref(), cfg.pass.as_ref());
+        let admin = cfg.admin;
+        if admin && auth.is_none() {
+            eprintln!(
+                "{TRACING_ADMIN_ENABLED} is set without {TRACING_METRICS_USER} or {TRACING_METRICS_PASS}, requests to {LOG_LEVEL_PATH} will be refused"
+            );
+        }
+        Self {
+            path,
+            auth,
+            admin,
+            filter: None,
+        }
+    }
+}
+
+/// The `Authorization` header value scrapes must send, if basic auth is
+/// configured, i.e. either the user or the password is set.
+fn basic_auth(user: Option<&str>, pass: Option<&Redacted>) -> Option<String> {
+    let pass = pass.map(|pass| pass.expose().as_str());
+    if user.is_none() && pass.is_none() {
+        return None;
+    }
+    if user.is_none() || pass.is_none() {
+        eprintln!(
+            "only one of {TRACING_METRICS_USER} and {TRACING_METRICS_PASS} is set, the other is taken to be empty"
+        );
+    }
+    let credentials = format!("{}:{}", user.unwrap_or_default(), pass.unwrap_or_default());
+    Some(format!("Basic {}", BASE64.encode(credentials)))
+}
+
+/// Compare in constant time, so as not to leak how much of the credentials
+/// matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Create a response with the status and an empty body.
+fn empty(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::default());
+    *response.status_mut() = status;
+    response
+}
+
+/// Create a response with the status and a plain text body.
+fn text(status: StatusCode, body: impl Into<Bytes>) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+    response
+}
+
+/// Replace the log filter with the directives in the body of a `PUT`
+/// request.
+async fn set_log_level(
+    req: Request<Incoming>,
+    filter: Option<ReloadHandle>,
+) -> Response<Full<Bytes>> {
+    if req.method() != Method::PUT {
+        let mut response = empty(StatusCode::METHOD_NOT_ALLOWED);
+        response
+            .headers_mut()
+            .insert(ALLOW, HeaderValue::from_static("PUT"));
+        return response;
+    }
+    let Some(handle) = filter.or_else(ReloadHandle::current) else {
+        return text(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "tracing is not initialized",
+        );
+    };
+
+    let body = match Limited::new(req.into_body(), MAX_DIRECTIVES_LEN)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(err) => return text(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    let Ok(directives) = std::str::from_utf8(&body) else {
+        return text(StatusCode::BAD_REQUEST, "directives must be UTF-8");
+    };
+    let directives = directives.trim();
+    match handle.reload(directives) {
+        Ok(()) => {
+            tracing::info!(directives, "log filter replaced");
+            empty(StatusCode::NO_CONTENT)
+        }
+        Err(err) => text(StatusCode::BAD_REQUEST, err.to_string()),
+    }
+}
+
+/// Answer health checks, and, if authorized, render the metrics in response
+/// to a scrape of the metrics path, or replace the log filter in response to
+/// a request to the admin endpoint, if enabled.
+async fn respond(
+    req: Request<Incoming>,
+    handle: PrometheusHandle,
+    config: Arc<ServerConfig>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.uri().path() == HEALTH_PATH {
+        return Ok(empty(StatusCode::OK));
+    }
+    let admin = config.admin && req.uri().path() == LOG_LEVEL_PATH;
+    if req.uri().path() != config.path && !admin {
+        return Ok(empty(StatusCode::NOT_FOUND));
+    }
+
+    let authorized = config.auth.as_deref().is_none_or(|expected| {
+        req.headers()
+            .get(AUTHORIZATION)
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), expected.as_bytes()))
+    });
+    // Changing the log filter is never open to anyone reaching the server.
+    if admin && config.auth.is_none() {
+        return Ok(text(
+            StatusCode::FORBIDDEN,
+            format!("{TRACING_METRICS_USER} and {TRACING_METRICS_PASS} must be set"),
+        ));
+    }
+    if !authorized {
+        let mut response = empty(StatusCode::UNAUTHORIZED);
+        response.headers_mut().insert(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"metrics\""),
+        );
+        return Ok(response);
+    }
+
+    if admin {
+        return Ok(set_log_level(req, config.filter.clone()).await);
+    }
+
+    let body = tokio::task::spawn_blocking(move || handle.render())
+        .await
+        .unwrap_or_default();
+    Ok(text(StatusCode::OK, body))
+}
+
+/// Serve the metrics over HTTP on the connection.
+fn serve_connection<S>(stream: S, handle: &PrometheusHandle, config: &Arc<ServerConfig>)
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let handle = handle.clone();
+    let config = Arc::clone(config);
+    let service = service_fn(move |req| respond(req, handle.clone(), Arc::clone(&config)));
+    tokio::spawn(async move {
+        let _ = http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await;
+    });
+}
+
+/// Aborts a task when dropped, e.g. to stop a background task of the
+/// exporter along with it.
+#[derive(Debug)]
+pub(crate) struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Delay after a first failure to accept a connection, e.g. as the process
+/// is out of file descriptors. Doubled on each further consecutive failure,
+/// up to [`MAX_ACCEPT_BACKOFF`].
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(5);
+/// Maximum delay between attempts to accept a connection.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Backoff between failed attempts to accept a connection, so that a
+/// persistent error does not spin the accept loop.
+#[derive(Debug, Default)]
+struct Backoff {
+    delay: Option<Duration>,
+}
+
+impl Backoff {
+    /// Record a failure, returning how long to wait before the next attempt.
+    fn fail(&mut self) -> Duration {
+        let delay = self.delay.map_or(MIN_ACCEPT_BACKOFF, |delay| {
+            (delay * 2).min(MAX_ACCEPT_BACKOFF)
+        });
+        self.delay = Some(delay);
+        delay
+    }
+
+    /// Record a success, resetting the delay.
+    const fn succeed(&mut self) {
+        self.delay = None;
+    }
+
+    /// Handle the result of accepting a connection, serving it, or waiting
+    /// before the next attempt on failure. The first of consecutive failures
+    /// is reported.
+    async fn serve<S>(
+        &mut self,
+        accepted: std::io::Result<S>,
+        handle: &PrometheusHandle,
+        config: &Arc<ServerConfig>,
+    ) where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        match accepted {
+            Ok(stream) => {
+                self.succeed();
+                serve_connection(stream, handle, config);
+            }
+            Err(err) => {
+                if self.delay.is_none() {
+                    eprintln!("failed to accept metrics connection: {err}");
+                }
+                tokio::time::sleep(self.fail()).await;
+            }
+        }
+    }
+}
+
+/// Serve the metrics over HTTP on the listener, and periodically drain the
+/// histograms, until aborted.
+async fn serve(
+    listener: Listener,
+    handle: PrometheusHandle,
+    config: ServerConfig,
+) -> std::io::Result<()> {
+    let config = Arc::new(config);
+    // Stop draining the histograms along with the exporter.
+    let _upkeep = AbortOnDrop(tokio::spawn(upkeep(handle.clone())).abort_handle());
+    let mut backoff = Backoff::default();
+
+    match listener {
+        Listener::Tcp(listener) => {
+            let listener = TcpListener::from_std(listener)?;
+            loop {
+                let accepted = listener.accept().await.map(|(stream, _)| stream);
+                backoff.serve(accepted, &handle, &config).await;
+            }
+        }
+        #[cfg(unix)]
+        Listener::Unix(listener) => {
+            let listener = tokio::net::UnixListener::from_std(listener)?;
+            loop {
+                let accepted = listener.accept().await.map(|(stream, _)| stream);
+                backoff.serve(accepted, &handle, &config).await;
+            }
+        }
+    }
+}
+
+/// Drain the histograms of the recorder periodically, forever.
+async fn upkeep(handle: PrometheusHandle) {
+    loop {
+        tokio::time::sleep(UPKEEP_INTERVAL).await;
+        handle.run_upkeep();
+    }
+}
+
+/// Spawn a task of the exporter, returning its abort handle. The task runs
+/// on the current tokio runtime if any, otherwise on a dedicated thread,
+/// which exits when the task is aborted.
+fn spawn(task: impl Future<Output = ()> + Send + 'static) -> Result<AbortHandle, BuildError> {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        return Ok(handle.spawn(task).abort_handle());
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| BuildError::FailedToCreateRuntime(e.to_string()))?;
+
+    let task = runtime.spawn(task);
+    let abort = task.abort_handle();
+    std::thread::Builder::new()
+        .name("metrics-exporter-prometheus".to_string())
+        .spawn(move || {
+            let _ = runtime.block_on(task);
+        })
+        .map_err(|e| BuildError::FailedToCreateRuntime(e.to_string()))?;
+
+    Ok(abort)
+}
+
+/// Spawn the exporter serving the metrics of the prometheus recorder with the
+/// handle on the listener. Returns the exporter, which stops when dropped.
+/// The exporter runs as by [`spawn`].
+///
+/// This mirrors [`PrometheusBuilder::install`], additionally applying the
+/// server config.
+///
+/// [`PrometheusBuilder::install`]: metrics_exporter_prometheus::PrometheusBuilder::install
+pub(crate) fn spawn_exporter(
+    listener: Listener,
+    handle: PrometheusHandle,
+    config: ServerConfig,
+) -> Result<AbortOnDrop, BuildError> {
+    let exporter = spawn(async move {
+        if let Err(err) = serve(listener, handle, config).await {
+            eprintln!("failed to serve metrics: {err}");
+        }
+    })?;
+    Ok(AbortOnDrop(exporter))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use metrics_exporter_prometheus::{
+        PrometheusBuilder,
+        PrometheusRecorder,
+    };
+    use std::io::{
+        Read,
+        Write,
+    };
+
+    /// Spawn an exporter on a free local port, returning its address.
+    fn spawn_local(config: ServerConfig) -> (PrometheusRecorder, AbortOnDrop, SocketAddr) {
+        let listener = Listener::tcp(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = SocketAddr::from(([127, 0, 0, 1], listener.port().unwrap()));
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let exporter = spawn_exporter(listener, recorder.handle(), config).unwrap();
+        (recorder, exporter, addr)
+    }
+
+    /// Send a `GET` request for the path on the stream, returning the raw
+    /// response.
+    fn get(stream: impl Read + Write, path: &str, authorization: Option<&str>) -> String {
+        send(stream, "GET", path, authorization, "")
+    }
+
+    /// Send a request with the body on the stream, returning the raw response.
+    fn send(
+        mut stream: impl Read + Write,
+        method: &str,
+        path: &str,
+        authorization: Option<&str>,
+        body: &str,
+    ) -> String {
+        let header = authorization
+            .map(|value| format!("authorization: {value}\r\n"))
+            .unwrap_or_default();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nhost: localhost\r\n{header}content-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn guard_stops_listener() {
+        let (_recorder, guard, addr) = spawn_local(Default::default());
+
+        let listening = || std::net::TcpStream::connect(addr).is_ok();
+        let eventually = |f: &dyn Fn() -> bool| {
+            (0..100).any(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                f()
+            })
+        };
+        assert!(eventually(&listening));
+
+        drop(guard);
+        assert!(eventually(&|| !listening()));
+    }
+
+    #[test]
+    fn basic_auth_required() {
+        let pass = Redacted::new("hunter2".to_owned());
+        let auth = basic_auth(Some("prometheus"), Some(&pass)).unwrap();
+        assert_eq!(
+            auth,
+            format!("Basic {}", BASE64.encode("prometheus:hunter2"))
+        );
+        assert_eq!(
+            basic_auth(None, Some(&pass)),
+            Some(format!("Basic {}", BASE64.encode(":hunter2")))
+        );
+        assert_eq!(basic_auth(None, None), None);
+
+        let (recorder, _guard, addr) = spawn_local(ServerConfig {
+            auth: Some(auth.clone()),
+            ..Default::default()
+        });
+        metrics::with_local_recorder(&recorder, || metrics::counter!("scrapes").increment(1));
+
+        let scrape = |authorization| {
+            get(
+                std::net::TcpStream::connect(addr).unwrap(),
+                DEFAULT_METRICS_PATH,
+                authorization,
+            )
+        };
+
+        let denied = scrape(None);
+        assert!(denied.starts_with("HTTP/1.1 401"), "{denied}");
+        assert!(denied.contains("www-authenticate: Basic"), "{denied}");
+        let wrong = format!("Basic {}", BASE64.encode("prometheus:hunter3"));
+        assert!(scrape(Some(&wrong)).starts_with("HTTP/1.1 401"));
+
+        let allowed = scrape(Some(&auth));
+        assert!(allowed.starts_with("HTTP/1.1 200"), "{allowed}");
+        assert!(allowed.contains("scrapes 1"), "{allowed}");
+    }
+
+    #[test]
+    fn metrics_path() {
+        let mut cfg = MetricsConfig {
+            path: "prometheus".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(ServerConfig::new(&cfg).path, DEFAULT_METRICS_PATH);
+        cfg.path = "/prometheus".to_owned();
+        let config = ServerConfig::new(&cfg);
+        assert_eq!(config.path, "/prometheus");
+
+        let (recorder, _guard, addr) = spawn_local(ServerConfig {
+            auth: Some("Basic secret".to_owned()),
+            ..config
+        });
+        metrics::with_local_recorder(&recorder, || metrics::counter!("scrapes").increment(1));
+        let get = |path, authorization| {
+            get(
+                std::net::TcpStream::connect(addr).unwrap(),
+                path,
+                authorization,
+            )
+        };
+
+        let scraped = get("/prometheus", Some("Basic secret"));
+        assert!(scraped.starts_with("HTTP/1.1 200"), "{scraped}");
+        assert!(scraped.contains("scrapes 1"), "{scraped}");
+        // Health checks don't authenticate.
+        assert!(get(HEALTH_PATH, None).starts_with("HTTP/1.1 200"));
+        assert!(get("/", Some("Basic secret")).starts_with("HTTP/1.1 404"));
+        assert!(get(DEFAULT_METRICS_PATH, Some("Basic secret")).starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn log_level_endpoint() {
+        let (_recorder, disabled, addr) = spawn_local(Default::default());
+        let put = get(
+            std::net::TcpStream::connect(addr).unwrap(),
+            LOG_LEVEL_PATH,
+            None,
+        );
+        assert!(put.starts_with("HTTP/1.1 404"), "{put}");
+        drop(disabled);
+
+        // Without credentials, the endpoint is refused rather than open.
+        let (_recorder, unprotected, addr) = spawn_local(ServerConfig {
+            admin: true,
+            ..Default::default()
+        });
+        let put = send(
+            std::net::TcpStream::connect(addr).unwrap(),
+            "PUT",
+            LOG_LEVEL_PATH,
+            None,
+            "trace",
+        );
+        assert!(put.starts_with("HTTP/1.1 403"), "{put}");
+        drop(unprotected);
+
+        let (filter, handle) =
+            tracing_subscriber::reload::Layer::new(tracing_subscriber::EnvFilter::new("debug"));
+        let subscriber =
+            tracing_subscriber::layer::SubscriberExt::with(tracing_subscriber::registry(), filter);
+        let (_recorder, _guard, addr) = spawn_local(ServerConfig {
+            auth: Some("Basic secret".to_owned()),
+            admin: true,
+            filter: Some(ReloadHandle(handle)),
+            ..Default::default()
+        });
+        let send = |method, authorization, body| {
+            send(
+                std::net::TcpStream::connect(addr).unwrap(),
+                method,
+                LOG_LEVEL_PATH,
+                authorization,
+                body,
+            )
+        };
+
+        assert!(send("PUT", None, "info").starts_with("HTTP/1.1 401"));
+        let get = send("GET", Some("Basic secret"), "");
+        assert!(get.starts_with("HTTP/1.1 405"), "{get}");
+        assert!(get.contains("allow: PUT"), "{get}");
+        let invalid = send("PUT", Some("Basic secret"), "info,my_crate=bogus");
+        assert!(invalid.starts_with("HTTP/1.1 400"), "{invalid}");
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(tracing::enabled!(tracing::Level::DEBUG));
+            let valid = send("PUT", Some("Basic secret"), "info\n");
+            assert!(valid.starts_with("HTTP/1.1 204"), "{valid}");
+            tracing::callsite::rebuild_interest_cache();
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        let path =
+            std::env::temp_dir().join(format!("rust-tracing-metrics-{}.sock", std::process::id()));
+        let cfg = MetricsConfig {
+            uds_path: Some(path.clone()),
+            ..Default::default()
+        };
+        // A socket left by a previous run is replaced.
+        drop(bind(&cfg).unwrap());
+        let listener = bind(&cfg).unwrap();
+        assert!(matches!(listener, Listener::Unix(_)));
+        assert_eq!(listener.port(), None);
+
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let _guard = spawn_exporter(listener, recorder.handle(), Default::default()).unwrap();
+        metrics::with_local_recorder(&recorder, || metrics::counter!("uds").increment(1));
+
+        let stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        let response = get(stream, DEFAULT_METRICS_PATH, None);
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.contains("uds 1"), "{response}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn exporter_on_entered_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let (_recorder, _exporter, addr) = {
+            let _enter = runtime.enter();
+            spawn_local(Default::default())
+        };
+
+        let listening = || std::net::TcpStream::connect(addr).is_ok();
+        let eventually = |f: &dyn Fn() -> bool| {
+            (0..100).any(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                f()
+            })
+        };
+        assert!(eventually(&listening));
+
+        // The exporter is a task of the runtime, rather than of a dedicated
+        // thread, so it stops with the runtime.
+        runtime.shutdown_background();
+        assert!(eventually(&|| !listening()));
+    }
+
+    #[test]
+    fn accept_backoff() {
+        let mut backoff = Backoff::default();
+        assert_eq!(backoff.fail(), MIN_ACCEPT_BACKOFF);
+        assert_eq!(backoff.fail(), MIN_ACCEPT_BACKOFF * 2);
+        assert!((0..20).all(|_| backoff.fail() <= MAX_ACCEPT_BACKOFF));
+        assert_eq!(backoff.fail(), MAX_ACCEPT_BACKOFF);
+        backoff.succeed();
+        assert_eq!(backoff.fail(), MIN_ACCEPT_BACKOFF);
+    }
+}
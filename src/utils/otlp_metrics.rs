@@ -0,0 +1,282 @@
+use metrics::{
+    Counter,
+    Gauge,
+    Histogram,
+    HistogramFn,
+    Key,
+    KeyName,
+    Metadata,
+    Recorder,
+    SharedString,
+    Unit,
+};
+use metrics_util::registry::{
+    AtomicStorage,
+    Registry,
+};
+use opentelemetry::{
+    KeyValue,
+    metrics::Meter,
+};
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        atomic::Ordering,
+    },
+};
+
+/// Convert the labels of a [`Key`] into OTEL attributes.
+fn attributes(key: &Key) -> Vec<KeyValue> {
+    key.labels()
+        .map(|label| KeyValue::new(label.key().to_owned(), label.value().to_owned()))
+        .collect()
+}
+
+/// Histogram handle that records directly into an OTEL histogram.
+struct OtelHistogram {
+    histogram: opentelemetry::metrics::Histogram<f64>,
+    attributes: Vec<KeyValue>,
+}
+
+impl HistogramFn for OtelHistogram {
+    fn record(&self, value: f64) {
+        self.histogram.record(value, &self.attributes);
+    }
+}
+
+/// Description and unit registered via the `describe_*` macros.
+type Description = (Option<Unit>, SharedString);
+
+/// A [`Recorder`] that forwards metrics recorded via the [`metrics`] crate to
+/// an OpenTelemetry [`Meter`].
+///
+/// Counters and gauges are stored locally, and observed by the meter provider
+/// on each export. Histogram observations are recorded directly into OTEL
+/// histograms.
+///
+/// This is usually obtained via [`OtelGuard::metrics_recorder`].
+///
+/// [`OtelGuard::metrics_recorder`]: crate::utils::otlp::OtelGuard::metrics_recorder
+#[derive(Clone)]
+pub struct OtelRecorder {
+    meter: Meter,
+    registry: Arc<Registry<Key, AtomicStorage>>,
+    descriptions: Arc<Mutex<HashMap<String, Description>>>,
+    observed: Arc<Mutex<HashSet<(&'static str, String)>>>,
+    histograms: Arc<Mutex<HashMap<String, opentelemetry::metrics::Histogram<f64>>>>,
+}
+
+impl core::fmt::Debug for OtelRecorder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OtelRecorder").finish_non_exhaustive()
+    }
+}
+
+impl OtelRecorder {
+    /// Create a new recorder, recording into instruments created by the
+    /// given [`Meter`].
+    pub fn new(meter: Meter) -> Self {
+        Self {
+            meter,
+            registry: Arc::new(Registry::atomic()),
+            descriptions: Default::default(),
+            observed: Default::default(),
+            histograms: Default::default(),
+        }
+    }
+
+    /// Whether the recorders record into the same instruments, i.e. are
+    /// clones of each other.
+    #[cfg(test)]
+    pub(crate) fn shares_instruments(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.registry, &other.registry)
+    }
+
+    fn describe(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.descriptions
+            .lock()
+            .unwrap()
+            .insert(key.as_str().to_owned(), (unit, description));
+    }
+
+    fn description(&self, name: &str) -> Option<Description> {
+        self.descriptions.lock().unwrap().get(name).cloned()
+    }
+
+    /// Returns true the first time it is called for a given kind and name.
+    fn first_observation(&self, kind: &'static str, name: &str) -> bool {
+        self.observed
+            .lock()
+            .unwrap()
+            .insert((kind, name.to_owned()))
+    }
+
+    fn observe_counter(&self, name: &str) {
+        if !self.first_observation("counter", name) {
+            return;
+        }
+
+        let registry = Arc::clone(&self.registry);
+        let target = name.to_owned();
+        let mut builder = self
+            .meter
+            .u64_observable_counter(name.to_owned())
+            .with_callback(move |observer| {
+                registry.visit_counters(|key, counter| {
+                    if key.name() == target {
+                        observer.observe(counter.load(Ordering::Acquire), &attributes(key));
+                    }
+                })
+            });
+        if let Some((unit, description)) = self.description(name) {
+            builder = builder.with_description(description.into_owned());
+            if let Some(unit) = unit {
+                builder = builder.with_unit(unit.as_canonical_label());
+            }
+        }
+        builder.build();
+    }
+
+    fn observe_gauge(&self, name: &str) {
+        if !self.first_observation("gauge", name) {
+            return;
+        }
+
+        let registry = Arc::clone(&self.registry);
+        let target = name.to_owned();
+        let mut builder = self
+            .meter
+            .f64_observable_gauge(name.to_owned())
+            .with_callback(move |observer| {
+                registry.visit_gauges(|key, gauge| {
+                    if key.name() == target {
+                        let value = f64::from_bits(gauge.load(Ordering::Acquire));
+                        observer.observe(value, &attributes(key));
+                    }
+                })
+            });
+        if let Some((unit, description)) = self.description(name) {
+            builder = builder.with_description(description.into_owned());
+            if let Some(unit) = unit {
+                builder = builder.with_unit(unit.as_canonical_label());
+            }
+        }
+        builder.build();
+    }
+
+    fn histogram(&self, name: &str) -> opentelemetry::metrics::Histogram<f64> {
+        let mut histograms = self.histograms.lock().unwrap();
+        if let Some(histogram) = histograms.get(name) {
+            return histogram.clone();
+        }
+
+        let mut builder = self.meter.f64_histogram(name.to_owned());
+        if let Some((unit, description)) = self.description(name) {
+            builder = builder.with_description(description.into_owned());
+            if let Some(unit) = unit {
+                builder = builder.with_unit(unit.as_canonical_label());
+            }
+        }
+        let histogram = builder.build();
+        histograms.insert(name.to_owned(), histogram.clone());
+        histogram
+    }
+}
+
+impl Recorder for OtelRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        self.observe_counter(key.name());
+        Counter::from_arc(self.registry.get_or_create_counter(key, Arc::clone))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        self.observe_gauge(key.name());
+        Gauge::from_arc(self.registry.get_or_create_gauge(key, Arc::clone))
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(Arc::new(OtelHistogram {
+            histogram: self.histogram(key.name()),
+            attributes: attributes(key),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{
+        InMemoryMetricExporter,
+        SdkMeterProvider,
+        data::{
+            Histogram as HistogramData,
+            Metric,
+            ResourceMetrics,
+            Sum,
+        },
+    };
+
+    fn find<'a>(metrics: &'a [ResourceMetrics], name: &str) -> &'a Metric {
+        metrics
+            .iter()
+            .flat_map(|rm| rm.scope_metrics.iter())
+            .flat_map(|sm| sm.metrics.iter())
+            .rfind(|m| m.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn records_into_meter() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(exporter.clone())
+            .build();
+        let recorder = OtelRecorder::new(provider.meter("test"));
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("requests", "route" => "/").increment(2);
+            metrics::counter!("requests", "route" => "/").increment(3);
+            metrics::gauge!("in_flight").set(7.0);
+            metrics::histogram!("latency").record(1.5);
+        });
+
+        provider.force_flush().unwrap();
+        let metrics = exporter.get_finished_metrics().unwrap();
+
+        let requests = find(&metrics, "requests");
+        let sum = requests.data.as_any().downcast_ref::<Sum<u64>>().unwrap();
+        assert_eq!(sum.data_points[0].value, 5);
+        assert_eq!(
+            sum.data_points[0].attributes,
+            vec![KeyValue::new("route", "/")]
+        );
+
+        let latency = find(&metrics, "latency");
+        let histogram = latency
+            .data
+            .as_any()
+            .downcast_ref::<HistogramData<f64>>()
+            .unwrap();
+        assert_eq!(histogram.data_points[0].count, 1);
+
+        find(&metrics, "in_flight");
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    sync::mpsc,
+    time::Duration,
+};
+
+/// Interval between samples taken by the [`Collector`].
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Record the stats of the current process via the [`metrics`] facade, as
+/// the gauges conventionally exported by prometheus clients:
+///
+/// - `process_cpu_seconds_total` - user and system CPU time spent, in whole
+///   seconds. This is a counter, unlike the others.
+/// - `process_resident_memory_bytes` - resident memory size.
+/// - `process_virtual_memory_bytes` - virtual memory size.
+/// - `process_threads` - number of OS threads.
+/// - `process_open_fds` - number of open file descriptors.
+/// - `process_max_fds` - soft limit on open file descriptors.
+/// - `process_start_time_seconds` - start time, in seconds since the epoch.
+///
+/// The stats are read from `/proc`, so nothing is recorded on platforms other
+/// than Linux. Stats that fail to be read are skipped.
+///
+/// [`init_metrics`] records these periodically if `TRACING_PROCESS_METRICS`
+/// is set.
+///
+/// [`init_metrics`]: crate::utils::metrics::init_metrics
+pub fn record_process_metrics() {
+    #[cfg(target_os = "linux")]
+    linux::record();
+}
+
+/// Record the stats of the tokio runtime via the [`metrics`] facade, as the
+/// `tokio_workers`, `tokio_alive_tasks` and `tokio_global_queue_depth`
+/// gauges.
+#[cfg(feature = "tokio")]
+pub fn record_runtime_metrics(handle: &tokio::runtime::Handle) {
+    let runtime = handle.metrics();
+    metrics::gauge!("tokio_workers").set(runtime.num_workers() as f64);
+    metrics::gauge!("tokio_alive_tasks").set(runtime.num_alive_tasks() as f64);
+    metrics::gauge!("tokio_global_queue_depth").set(runtime.global_queue_depth() as f64);
+}
+
+/// Records the process stats, and the stats of the given runtime if any,
+/// every [`SAMPLE_INTERVAL`] on a dedicated thread, until dropped.
+#[derive(Debug)]
+pub(crate) struct Collector {
+    _stop: mpsc::Sender<()>,
+}
+
+impl Collector {
+    /// Start collecting. The stats are recorded via the [`metrics`] facade,
+    /// so to the global recorder, independently of how it exports them.
+    pub(crate) fn spawn(
+        #[cfg(feature = "tokio")] runtime: Option<tokio::runtime::Handle>,
+    ) -> std::io::Result<Self> {
+        let (stop, stopped) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("process-metrics".to_owned())
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stopped.recv_timeout(SAMPLE_INTERVAL)
+                {
+                    record_process_metrics();
+                    #[cfg(feature = "tokio")]
+                    if let Some(runtime) = &runtime {
+                        record_runtime_metrics(runtime);
+                    }
+                }
+            })?;
+        Ok(Self { _stop: stop })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs;
+
+    /// Clock ticks per second of the CPU times in `/proc`, i.e. `USER_HZ`,
+    /// which is 100 on all common architectures.
+    const USER_HZ: f64 = 100.0;
+
+    /// Read a `kB` value from `/proc/self/status`, in bytes.
+    pub(super) fn status_bytes(status: &str, key: &str) -> Option<u64> {
+        let kb = status_value(status, key)?.strip_suffix(" kB")?;
+        kb.trim().parse::<u64>().ok().map(|kb| kb * 1024)
+    }
+
+    /// Read a value from `/proc/self/status`.
+    pub(super) fn status_value<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+        status.lines().find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            (k == key).then(|| v.trim())
+        })
+    }
+
+    /// Read the 1-based field of `/proc/self/stat`, counting past the
+    /// parenthesized command name, which may contain spaces.
+    pub(super) fn stat_field(stat: &str, field: usize) -> Option<f64> {
+        let (_, rest) = stat.rsplit_once(')')?;
+        rest.split_whitespace().nth(field - 3)?.parse().ok()
+    }
+
+    /// The system boot time, in seconds since the epoch.
+    fn boot_time() -> Option<f64> {
+        fs::read_to_string("/proc/stat")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// The soft limit on open file descriptors.
+    fn max_fds() -> Option<u64> {
+        fs::read_to_string("/proc/self/limits")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("Max open files"))?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+
+    /// Record the process stats. See [`super::record_process_metrics`].
+    pub(super) fn record() {
+        if let Ok(stat) = fs::read_to_string("/proc/self/stat") {
+            if let (Some(utime), Some(stime)) = (stat_field(&stat, 14), stat_field(&stat, 15)) {
+                metrics::counter!("process_cpu_seconds_total")
+                    .absolute(((utime + stime) / USER_HZ) as u64);
+            }
+            if let (Some(start), Some(boot)) = (stat_field(&stat, 22), boot_time()) {
+                metrics::gauge!("process_start_time_seconds").set(boot + start / USER_HZ);
+            }
+        }
+
+        if let Ok(status) = fs::read_to_string("/proc/self/status") {
+            if let Some(rss) = status_bytes(&status, "VmRSS") {
+                metrics::gauge!("process_resident_memory_bytes").set(rss as f64);
+            }
+            if let Some(vsize) = status_bytes(&status, "VmSize") {
+                metrics::gauge!("process_virtual_memory_bytes").set(vsize as f64);
+            }
+            if let Some(threads) = status_value(&status, "Threads").and_then(|t| t.parse().ok()) {
+                metrics::gauge!("process_threads").set::<f64>(threads);
+            }
+        }
+
+        if let Ok(fds) = fs::read_dir("/proc/self/fd") {
+            metrics::gauge!("process_open_fds").set(fds.count() as f64);
+        }
+        if let Some(max) = max_fds() {
+            metrics::gauge!("process_max_fds").set(max as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_proc() {
+        use linux::{
+            stat_field,
+            status_bytes,
+            status_value,
+        };
+
+        let stat = "42 (my (odd) cmd) S 1 42 42 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 3 0 1000 0";
+        assert_eq!(stat_field(stat, 14), Some(250.0));
+        assert_eq!(stat_field(stat, 15), Some(50.0));
+        assert_eq!(stat_field(stat, 20), Some(3.0));
+        assert_eq!(stat_field(stat, 22), Some(1000.0));
+
+        let status = "Name:\tcmd\nVmSize:\t  2048 kB\nVmRSS:\t   512 kB\nThreads:\t3\n";
+        assert_eq!(status_bytes(status, "VmSize"), Some(2048 * 1024));
+        assert_eq!(status_bytes(status, "VmRSS"), Some(512 * 1024));
+        assert_eq!(status_value(status, "Threads"), Some("3"));
+        assert_eq!(status_bytes(status, "VmSwap"), None);
+    }
+
+    #[test]
+    fn records_metrics() {
+        #[cfg(feature = "tokio")]
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            record_process_metrics();
+            #[cfg(feature = "tokio")]
+            record_runtime_metrics(runtime.handle());
+        });
+
+        let rendered = handle.render();
+        #[cfg(feature = "tokio")]
+        assert!(rendered.contains("tokio_workers 2"), "{rendered}");
+        #[cfg(target_os = "linux")]
+        for name in [
+            "process_cpu_seconds_total",
+            "process_resident_memory_bytes",
+            "process_open_fds",
+            "process_start_time_seconds",
+        ] {
+            assert!(rendered.contains(name), "{rendered}");
+        }
+        #[cfg(target_os = "linux")]
+        assert!(
+            rendered.contains("# TYPE process_cpu_seconds_total counter"),
+            "{rendered}"
+        );
+    }
+}
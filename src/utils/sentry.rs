@@ -0,0 +1,92 @@
+use crate::utils::{
+    from_env::{
+        FromEnvErr,
+        parse_env_if_present,
+    },
+    tracing::BoxedLayer,
+};
+use sentry::{
+    ClientInitGuard,
+    ClientOptions,
+    integrations::tracing::EventFilter,
+    types::Dsn,
+};
+use tracing::{
+    Level,
+    Metadata,
+};
+use tracing_subscriber::Layer;
+
+const SENTRY_DSN: &str = "SENTRY_DSN";
+
+/// Guard of the Sentry client, flushing pending events when dropped.
+pub(crate) struct SentryGuard(ClientInitGuard);
+
+impl core::fmt::Debug for SentryGuard {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SentryGuard")
+            .field("enabled", &self.0.is_enabled())
+            .finish()
+    }
+}
+
+/// Forward `ERROR` and `WARN` events to Sentry as events, and attach `INFO`
+/// events to them as breadcrumbs.
+fn event_filter(metadata: &Metadata<'_>) -> EventFilter {
+    match *metadata.level() {
+        Level::ERROR | Level::WARN => EventFilter::Event,
+        Level::INFO => EventFilter::Breadcrumb,
+        Level::DEBUG | Level::TRACE => EventFilter::Ignore,
+    }
+}
+
+/// Create the Sentry client and its tracing layer, if `SENTRY_DSN` is set.
+///
+/// The returned guard flushes pending events when dropped. Spans are not
+/// sent to Sentry, as they are exported via OTLP.
+///
+/// ## Env Reads
+///
+/// - `SENTRY_DSN` - The Sentry DSN to report to. If missing, or invalid,
+///   Sentry is disabled.
+/// - `SENTRY_ENVIRONMENT` and `SENTRY_RELEASE` - As read by the Sentry
+///   client.
+pub(crate) fn layer() -> Option<(BoxedLayer, SentryGuard)> {
+    let dsn: Dsn = parse_env_if_present(SENTRY_DSN)
+        .inspect_err(|e| {
+            if matches!(e, FromEnvErr::ParseError(_)) {
+                eprintln!("{e}")
+            }
+        })
+        .ok()?;
+
+    let guard = sentry::init(ClientOptions {
+        dsn: Some(dsn),
+        ..Default::default()
+    });
+    let layer = sentry::integrations::tracing::layer()
+        .event_filter(event_filter)
+        .span_filter(|_| false);
+    Some((layer.boxed(), SentryGuard(guard)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[serial_test::serial]
+    fn layer_from_env() {
+        unsafe { std::env::remove_var(SENTRY_DSN) };
+        assert!(layer().is_none());
+
+        unsafe { std::env::set_var(SENTRY_DSN, "not a dsn") };
+        assert!(layer().is_none());
+
+        unsafe { std::env::set_var(SENTRY_DSN, "https://public@sentry.example.com/1") };
+        let (_, guard) = layer().unwrap();
+        assert!(guard.0.is_enabled());
+
+        unsafe { std::env::remove_var(SENTRY_DSN) };
+    }
+}
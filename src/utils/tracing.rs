@@ -1,101 +1,2359 @@
+#[cfg(feature = "otlp")]
+use crate::utils::otlp::{
+    OtelConfig,
+    OtelReload,
+    sdk_disabled,
+};
 use crate::utils::{
-    from_env::FromEnvVar,
-    otlp::{
-        OtelConfig,
-        OtelGuard,
+    filter::LogFilter,
+    from_env::{
+        EnvItemInfo,
+        FromEnv,
+        FromEnvErr,
+        FromEnvVar,
+        parse_env_if_present,
+        warn_unknown_vars,
+    },
+    metrics::MetricsConfig,
+    otlp::OtelGuard,
+};
+use std::{
+    cell::{
+        Cell,
+        RefCell,
+    },
+    io::IsTerminal,
+    sync::{
+        Mutex,
+        MutexGuard,
+        OnceLock,
+        PoisonError,
+    },
+};
+use tracing::{
+    Span,
+    span::EnteredSpan,
+};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{
+        RollingFileAppender,
+        Rotation,
     },
 };
 use tracing_subscriber::{
     Layer,
-    filter::EnvFilter,
+    Registry,
+    filter::{
+        EnvFilter,
+        FilterExt,
+        LevelFilter,
+        ParseError,
+        filter_fn,
+    },
+    fmt::{
+        FmtContext,
+        FormatEvent,
+        FormatFields,
+        Layer as FmtLayer,
+        MakeWriter,
+        format::{
+            DefaultFields,
+            FmtSpan,
+            Format,
+            Writer,
+        },
+        time::{
+            ChronoLocal,
+            ChronoUtc,
+            Uptime,
+        },
+        writer::{
+            BoxMakeWriter,
+            MakeWriterExt,
+        },
+    },
     layer::SubscriberExt,
-    util::SubscriberInitExt,
+    registry::LookupSpan,
+    reload,
+    util::{
+        SubscriberInitExt,
+        TryInitError,
+    },
 };
 
 const TRACING_LOG_JSON: &str = "TRACING_LOG_JSON";
+const TRACING_FILTER: &str = "TRACING_FILTER";
+const RUST_LOG: &str = "RUST_LOG";
+const TRACING_LOG_FORMAT: &str = "TRACING_LOG_FORMAT";
+const TRACING_LOG_DIR: &str = "TRACING_LOG_DIR";
+const TRACING_FILE_LEVEL: &str = "TRACING_FILE_LEVEL";
+const TRACING_TO_STDOUT: &str = "TRACING_TO_STDOUT";
+const TRACING_LOG_ROTATION: &str = "TRACING_LOG_ROTATION";
+const TRACING_ROOT_SPAN_NAME: &str = "TRACING_ROOT_SPAN_NAME";
+const TRACING_LOG_TIME_FORMAT: &str = "TRACING_LOG_TIME_FORMAT";
+const TRACING_LOG_TIME_UTC: &str = "TRACING_LOG_TIME_UTC";
+const TRACING_LOG_ANSI: &str = "TRACING_LOG_ANSI";
+const TRACING_LOG_SPAN_EVENTS: &str = "TRACING_LOG_SPAN_EVENTS";
+const TRACING_LOG_THREAD_NAMES: &str = "TRACING_LOG_THREAD_NAMES";
+const TRACING_LOG_THREAD_IDS: &str = "TRACING_LOG_THREAD_IDS";
+const TRACING_LOG_TRACE_IDS: &str = "TRACING_LOG_TRACE_IDS";
+const TRACING_STDOUT_LEVEL: &str = "TRACING_STDOUT_LEVEL";
+#[cfg(feature = "journald")]
+const TRACING_TO_JOURNALD: &str = "TRACING_TO_JOURNALD";
+#[cfg(feature = "journald")]
+const TRACING_JOURNALD_LEVEL: &str = "TRACING_JOURNALD_LEVEL";
+#[cfg(feature = "journald")]
+const TRACING_JOURNALD_IDENTIFIER: &str = "TRACING_JOURNALD_IDENTIFIER";
+#[cfg(feature = "journald")]
+const TRACING_JOURNALD_FIELD_PREFIX: &str = "TRACING_JOURNALD_FIELD_PREFIX";
+const TRACING_WARN_UNKNOWN_VARS: &str = "TRACING_WARN_UNKNOWN_VARS";
+const NO_COLOR: &str = "NO_COLOR";
+
+/// Log filter used if neither `TRACING_FILTER` nor `RUST_LOG` is set.
+const DEFAULT_FILTER: &str = "info";
+
+/// File name prefix for log files written to `TRACING_LOG_DIR`.
+const LOG_FILE_PREFIX: &str = "tracing";
+
+/// How often the log file in `TRACING_LOG_DIR` is rotated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    /// Start a new file every day, `daily`.
+    #[default]
+    Daily,
+    /// Start a new file every hour, `hourly`.
+    Hourly,
+    /// Never rotate, `never`.
+    Never,
+}
+
+impl core::str::FromStr for LogRotation {
+    type Err = LogRotationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "daily" => Ok(Self::Daily),
+            "hourly" => Ok(Self::Hourly),
+            "never" => Ok(Self::Never),
+            _ => Err(LogRotationParseError(s.to_owned())),
+        }
+    }
+}
+
+impl FromEnvVar for LogRotation {
+    type Error = LogRotationParseError;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_env_if_present(env_var)
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Daily => Self::DAILY,
+            LogRotation::Hourly => Self::HOURLY,
+            LogRotation::Never => Self::NEVER,
+        }
+    }
+}
+
+/// Error parsing a [`LogRotation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRotationParseError(String);
+
+impl core::fmt::Display for LogRotationParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid log rotation: {}", self.0)
+    }
+}
+
+impl core::error::Error for LogRotationParseError {}
+
+/// Error returned by [`ReloadHandle::reload`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReloadError {
+    /// The filter directives failed to parse.
+    Filter(ParseError),
+    /// The subscriber holding the filter was dropped.
+    Reload(reload::Error),
+}
+
+impl From<ParseError> for ReloadError {
+    fn from(err: ParseError) -> Self {
+        Self::Filter(err)
+    }
+}
+
+impl From<reload::Error> for ReloadError {
+    fn from(err: reload::Error) -> Self {
+        Self::Reload(err)
+    }
+}
+
+impl core::fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Filter(err) => write!(f, "invalid log filter: {err}"),
+            Self::Reload(err) => write!(f, "failed to reload log filter: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for ReloadError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Filter(err) => Some(err),
+            Self::Reload(err) => Some(err),
+        }
+    }
+}
+
+/// Handle to replace the log filter of the fmt layer at runtime, e.g. from a
+/// `SIGHUP` handler or an admin endpoint. Obtained via
+/// [`TracingGuard::reload_handle`].
+#[derive(Debug, Clone)]
+pub struct ReloadHandle(pub(crate) reload::Handle<EnvFilter, Registry>);
+
+impl ReloadHandle {
+    /// Replace the log filter with one parsed from the given directives, in
+    /// the same syntax as `RUST_LOG`, e.g. `debug` or `my_crate=trace,info`.
+    ///
+    /// The filter is replaced until the next call to this function or to
+    /// [`reinit_tracing`], which re-reads `TRACING_FILTER` and `RUST_LOG`.
+    pub fn reload(&self, directives: &str) -> Result<(), ReloadError> {
+        let filter = EnvFilter::try_new(directives)?;
+        self.0.reload(filter)?;
+        Ok(())
+    }
+
+    /// The handle of the tracing installed by [`init_tracing`], if any.
+    pub(crate) fn current() -> Option<Self> {
+        RELOAD.get().map(|handles| Self(handles.filter.clone()))
+    }
+}
+
+/// Output format of log lines, as named in `TRACING_LOG_FORMAT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The default single-line format, `full`.
+    #[default]
+    Full,
+    /// A denser single-line format, `compact`.
+    Compact,
+    /// A multi-line format for local debugging, `pretty`.
+    Pretty,
+    /// Newline-delimited JSON, `json`.
+    Json,
+}
+
+impl core::str::FromStr for LogFormat {
+    type Err = LogFormatParseError;
 
-/// Install a format layer based on the `TRACING_LOG_JSON` environment
-/// variable, and then install the registr
-macro_rules! install_fmt {
-    (json @ $registry:ident, $filter:ident) => {{
-        let fmt = tracing_subscriber::fmt::layer().json().with_filter($filter);
-        $registry.with(fmt).init();
-    }};
-    (log @ $registry:ident, $filter:ident) => {{
-        let fmt = tracing_subscriber::fmt::layer().with_filter($filter);
-        $registry.with(fmt).init();
-    }};
-    ($registry:ident) => {{
-        let json = bool::from_env_var(TRACING_LOG_JSON).unwrap_or(false);
-        let filter = EnvFilter::from_default_env();
-        if json {
-            install_fmt!(json @ $registry, filter);
-        } else {
-            install_fmt!(log @ $registry, filter);
-        }
-    }};
-}
-
-/// Init tracing, returning an optional guard for the OTEL provider.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "compact" => Ok(Self::Compact),
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(LogFormatParseError(s.to_owned())),
+        }
+    }
+}
+
+impl FromEnvVar for LogFormat {
+    type Error = LogFormatParseError;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_env_if_present(env_var)
+    }
+}
+
+impl LogFormat {
+    /// Load the format from `TRACING_LOG_FORMAT`, falling back to
+    /// `TRACING_LOG_JSON`, and then to detecting whether stdout is a terminal.
+    fn from_env() -> Self {
+        Self::from_env_or_detect(std::io::stdout().is_terminal())
+    }
+
+    /// Load the format from `TRACING_LOG_FORMAT`. If it is unset, `json` if
+    /// `TRACING_LOG_JSON` is true and `full` if it is false. If neither is
+    /// set, `pretty` when writing to a terminal and `json` otherwise.
+    fn from_env_or_detect(is_terminal: bool) -> Self {
+        match Self::from_env_var(TRACING_LOG_FORMAT) {
+            Ok(format) => format,
+            Err(err) => {
+                if matches!(err, FromEnvErr::ParseError(_)) {
+                    eprintln!("{err}");
+                }
+                match String::from_env_var(TRACING_LOG_JSON) {
+                    Ok(_) if bool::from_env_var(TRACING_LOG_JSON).unwrap_or(false) => Self::Json,
+                    Ok(_) => Self::Full,
+                    Err(_) if is_terminal => Self::Pretty,
+                    Err(_) => Self::Json,
+                }
+            }
+        }
+    }
+}
+
+/// Error parsing a [`LogFormat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFormatParseError(String);
+
+impl core::fmt::Display for LogFormatParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid log format: {}", self.0)
+    }
+}
+
+impl core::error::Error for LogFormatParseError {}
+
+/// Parses a comma-separated list of span lifecycle events to log, from
+/// `none`, `new`, `enter`, `exit`, `close`, `active` and `full`, e.g.
+/// `new,close`.
+impl FromEnvVar for FmtSpan {
+    type Error = SpanEventsParseError;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        let s = String::from_env_var(env_var).map_err(FromEnvErr::infallible_into)?;
+        s.split(',')
+            .map(|event| {
+                match event.trim().to_ascii_lowercase().as_str() {
+                    "none" => Ok(Self::NONE),
+                    "new" => Ok(Self::NEW),
+                    "enter" => Ok(Self::ENTER),
+                    "exit" => Ok(Self::EXIT),
+                    "close" => Ok(Self::CLOSE),
+                    "active" => Ok(Self::ACTIVE),
+                    "full" => Ok(Self::FULL),
+                    _ => Err(SpanEventsParseError(event.to_owned())),
+                }
+            })
+            .try_fold(Self::NONE, |events, event| Ok(events | event?))
+            .map_err(FromEnvErr::parse_error)
+    }
+}
+
+/// Error parsing the span events in `TRACING_LOG_SPAN_EVENTS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanEventsParseError(String);
+
+impl core::fmt::Display for SpanEventsParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid span event: {}", self.0)
+    }
+}
+
+impl core::error::Error for SpanEventsParseError {}
+
+/// Timestamp format of log lines, as named in `TRACING_LOG_TIME_FORMAT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogTimeFormat {
+    /// The `tracing_subscriber` default, UTC with microsecond precision,
+    /// `system`.
+    #[default]
+    System,
+    /// RFC 3339, in UTC if `TRACING_LOG_TIME_UTC` is set and local time
+    /// otherwise, `rfc3339`.
+    Rfc3339,
+    /// Time elapsed since tracing was initialized, `uptime`.
+    Uptime,
+    /// No timestamp, `none`.
+    None,
+}
+
+impl core::str::FromStr for LogTimeFormat {
+    type Err = LogTimeFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "system" => Ok(Self::System),
+            "rfc3339" => Ok(Self::Rfc3339),
+            "uptime" => Ok(Self::Uptime),
+            "none" => Ok(Self::None),
+            _ => Err(LogTimeFormatParseError(s.to_owned())),
+        }
+    }
+}
+
+impl FromEnvVar for LogTimeFormat {
+    type Error = LogTimeFormatParseError;
+
+    fn from_env_var(env_var: &str) -> Result<Self, FromEnvErr<Self::Error>> {
+        parse_env_if_present(env_var)
+    }
+}
+
+/// Error parsing a [`LogTimeFormat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogTimeFormatParseError(String);
+
+impl core::fmt::Display for LogTimeFormatParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid log time format: {}", self.0)
+    }
+}
+
+impl core::error::Error for LogTimeFormatParseError {}
+
+/// Guard returned when initializing tracing. This guard should be kept alive
+/// for the lifetime of the program. Dropping it shuts down the OTEL provider,
+/// if any, and flushes buffered lines to the log file, if any.
 ///
-/// If the OTEL environment variables are not set, this function will
-/// initialize a basic tracing subscriber with a `fmt` layer. If the
-/// environment variables are set, it will initialize the OTEL provider
-/// with the specified configuration, as well as the `fmt` layer.
+/// If `TRACING_ROOT_SPAN_NAME` is set, the guard also holds the root span,
+/// which is entered on the thread that initialized tracing for the lifetime
+/// of the guard. Only work on that thread is nested under the root span.
+/// Other threads may enter it via [`TracingGuard::root_span`]. The root span
+/// is exited when the guard is dropped on that thread. If the guard is
+/// dropped elsewhere, e.g. after being moved into a task, the root span stays
+/// entered on that thread, and is neither closed nor exported.
 ///
-/// ## Env Reads
+/// ## Drop order
+///
+/// The root span is exited and closed first, so that it is exported before
+/// the OTEL provider is flushed and shut down. Pending Sentry events are
+/// flushed next, if the `sentry` feature is enabled. The log file is flushed
+/// last.
+#[derive(Debug)]
+pub struct TracingGuard {
+    root: Option<RootSpan>,
+    otel: Option<OtelGuard>,
+    #[cfg(feature = "sentry")]
+    sentry: Option<super::sentry::SentryGuard>,
+    file: Option<WorkerGuard>,
+    #[cfg(feature = "journald")]
+    journald: bool,
+    filter: ReloadHandle,
+}
+
+impl TracingGuard {
+    /// A handle to replace the log filter at runtime.
+    pub const fn reload_handle(&self) -> &ReloadHandle {
+        &self.filter
+    }
+
+    /// The OTEL guard, if OTLP is enabled. Always [`None`] without the `otlp`
+    /// feature.
+    pub const fn otel(&self) -> Option<&OtelGuard> {
+        self.otel.as_ref()
+    }
+
+    /// The root span, if `TRACING_ROOT_SPAN_NAME` is set.
+    pub fn root_span(&self) -> Option<&Span> {
+        self.root.as_ref().map(|root| &root.0)
+    }
+
+    /// Returns `true` if logs are written to `TRACING_LOG_DIR`.
+    pub const fn is_file_logging(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// A guard holding nothing but the reload handle of the tracing installed
+    /// by an earlier init, if any.
+    pub(crate) fn existing() -> Option<Self> {
+        ReloadHandle::current().map(|filter| {
+            Self {
+                root: None,
+                otel: None,
+                #[cfg(feature = "sentry")]
+                sentry: None,
+                file: None,
+                #[cfg(feature = "journald")]
+                journald: false,
+                filter,
+            }
+        })
+    }
+
+    /// Shut down as when dropped, but without blocking the async runtime
+    /// while the OTEL provider flushes. See `OtelGuard::shutdown`. Errors are
+    /// printed, and reported by the `shutdown complete` event.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if OTLP is enabled and it is not called
+    /// within a tokio runtime.
+    pub async fn shutdown(self) {
+        let Self {
+            root,
+            otel,
+            #[cfg(feature = "sentry")]
+            sentry,
+            file,
+            #[cfg(feature = "journald")]
+                journald: _,
+            filter: _,
+        } = self;
+        drop(root);
+        #[cfg(feature = "otlp")]
+        if let Some(otel) = otel {
+            let _ = otel.shutdown().await;
+        }
+        #[cfg(not(feature = "otlp"))]
+        let _ = otel;
+        #[cfg(feature = "sentry")]
+        drop(sentry);
+        drop(file);
+    }
+
+    /// Shut down as [`TracingGuard::shutdown`] when the process receives
+    /// `SIGINT` or `SIGTERM`, then exit the process with the conventional
+    /// status of the signal, `130` or `143`.
+    ///
+    /// This installs process-global signal handlers, replacing the default
+    /// behavior of these signals for the whole process. The guard is moved
+    /// into a task spawned on the current runtime, and is dropped as usual
+    /// if the program exits otherwise. Prefer
+    /// [`AppGuard::install_shutdown_handler`] if metrics are served too.
+    ///
+    /// ## Panics
+    ///
+    /// This function will panic if not called within a tokio runtime, or if
+    /// the signal handlers cannot be installed.
+    ///
+    /// [`AppGuard::install_shutdown_handler`]: crate::AppGuard::install_shutdown_handler
+    #[cfg(feature = "signal")]
+    pub fn install_shutdown_handler(self) {
+        exit_on_signal(self.shutdown());
+    }
+}
+
+/// Await `shutdown` when the process receives `SIGINT` or `SIGTERM`, then
+/// exit the process with the status of the signal.
+#[cfg(feature = "signal")]
+pub(crate) fn exit_on_signal(shutdown: impl Future<Output = ()> + Send + 'static) {
+    let signal = shutdown_signal();
+    tokio::spawn(async move {
+        let status = signal.await;
+        tracing::info!(status, "signal received, shutting down");
+        shutdown.await;
+        std::process::exit(status);
+    });
+}
+
+/// Install the `SIGINT` and `SIGTERM` handlers, returning a future resolving
+/// to the exit status of the first signal received.
+#[cfg(all(feature = "signal", unix))]
+fn shutdown_signal() -> impl Future<Output = i32> {
+    use tokio::signal::unix::{
+        SignalKind,
+        signal,
+    };
+
+    let mut interrupt = signal(SignalKind::interrupt()).expect("failed to install SIGINT handler");
+    let mut terminate = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    std::future::poll_fn(move |cx| {
+        if interrupt.poll_recv(cx).is_ready() {
+            return std::task::Poll::Ready(130);
+        }
+        terminate.poll_recv(cx).map(|_| 143)
+    })
+}
+
+/// Without unix signals, only ctrl-c is handled.
+#[cfg(all(feature = "signal", not(unix)))]
+fn shutdown_signal() -> impl Future<Output = i32> {
+    async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install ctrl-c handler");
+        130
+    }
+}
+
+/// Error returned by [`try_init_tracing`] when the subscriber could not be
+/// installed, e.g. as another global subscriber, possibly composed from
+/// [`tracing_layers`], or a `log` logger was already installed.
+#[derive(Debug)]
+pub struct TracingInitError(TryInitError);
+
+impl core::fmt::Display for TracingInitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "failed to install the tracing subscriber: {}", self.0)
+    }
+}
+
+impl core::error::Error for TracingInitError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Boxed layer over the [`Registry`].
+pub(crate) type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Settings taking precedence over the environment, set via
+/// [`TracingBuilder`].
 ///
-/// - `TRACING_LOG_JSON` - If set, will enable JSON logging.
-/// - As [`OtelConfig`] documentation for env var information.
+/// [`TracingBuilder`]: crate::TracingBuilder
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Overrides {
+    pub(crate) format: Option<LogFormat>,
+    pub(crate) filter: Option<LogFilter>,
+    #[cfg(feature = "otlp")]
+    pub(crate) otlp_endpoint: Option<url::Url>,
+    #[cfg(feature = "otlp")]
+    pub(crate) otlp_level: Option<tracing::Level>,
+    #[cfg(feature = "otlp")]
+    pub(crate) otlp_tracer_name: Option<String>,
+    #[cfg(feature = "journald")]
+    pub(crate) journald: bool,
+}
+
+impl Overrides {
+    /// The log format, falling back to the environment.
+    fn format(&self) -> LogFormat {
+        self.format.unwrap_or_else(LogFormat::from_env)
+    }
+
+    /// The configured log filter directives, falling back to
+    /// `TRACING_FILTER`, and then to `RUST_LOG`, if unset or invalid. Invalid
+    /// directives are reported.
+    fn directives(&self) -> Option<LogFilter> {
+        let from_env = |var: &str| {
+            LogFilter::from_env_var(var)
+                .inspect_err(|e| {
+                    if matches!(e, FromEnvErr::ParseError(_)) {
+                        eprintln!("invalid {var}: {e}")
+                    }
+                })
+                .ok()
+        };
+        self.filter
+            .clone()
+            .or_else(|| from_env(TRACING_FILTER))
+            .or_else(|| from_env(RUST_LOG))
+    }
+
+    /// The log filter, from the configured directives, defaulting to `info`.
+    fn filter(&self) -> EnvFilter {
+        self.directives()
+            .map_or_else(|| EnvFilter::new(DEFAULT_FILTER), EnvFilter::from)
+    }
+
+    /// Whether to log to journald, falling back to `TRACING_TO_JOURNALD`.
+    #[cfg(feature = "journald")]
+    fn journald(&self) -> bool {
+        self.journald || bool::from_env_var(TRACING_TO_JOURNALD).unwrap_or(false)
+    }
+
+    /// Load the OTEL config and instantiate the provider, if OTLP is enabled.
+    /// The endpoint, level and tracer name fall back to the environment. `OTEL_SDK_DISABLED`
+    /// disables OTLP even if an endpoint is configured.
+    #[cfg(feature = "otlp")]
+    fn otel_guard(&self) -> Option<OtelGuard> {
+        if sdk_disabled() {
+            return None;
+        }
+        let mut cfg = match &self.otlp_endpoint {
+            Some(endpoint) => OtelConfig::from_env_with_endpoint(Some(endpoint.clone())),
+            None => OtelConfig::load()?,
+        };
+        if let Some(level) = self.otlp_level {
+            cfg.level = level;
+        }
+        if let Some(name) = &self.otlp_tracer_name {
+            cfg.tracer_name.clone_from(name);
+        }
+        // Export with the same filter as the fmt layer, unless `OTEL_FILTER`
+        // selects targets specifically. The `info` default is not applied, so
+        // that the OTLP level keeps taking effect.
+        if cfg.filter.is_none() {
+            cfg.filter = self.directives();
+        }
+        cfg.try_provider().inspect_err(|e| eprintln!("{e}")).ok()
+    }
+
+    /// OTLP is never enabled without the `otlp` feature.
+    #[cfg(not(feature = "otlp"))]
+    const fn otel_guard(&self) -> Option<OtelGuard> {
+        None
+    }
+}
+
+/// Handles to the reloadable layers installed by [`init_tracing`], used by
+/// [`reinit_tracing`].
+struct Reload {
+    fmt: reload::Handle<BoxedLayer, Registry>,
+    filter: reload::Handle<EnvFilter, Registry>,
+    /// [`None`] if OTLP was disabled at init, in which case its layers
+    /// are not installed.
+    #[cfg(feature = "otlp")]
+    otel: Option<OtelReload>,
+    overrides: Overrides,
+}
+
+static RELOAD: OnceLock<Reload> = OnceLock::new();
+
+/// Held while initializing, so that concurrent first calls do not all build
+/// and try to install layers, with all but one failing.
+static INIT: Mutex<()> = Mutex::new(());
+
+/// Lock [`INIT`]. A panic during a previous init leaves nothing to clean up,
+/// so poisoning is ignored.
+pub(crate) fn init_lock() -> MutexGuard<'static, ()> {
+    INIT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+thread_local! {
+    /// Depth of [`with_log_suppressed`] calls on the current thread.
+    static SUPPRESSED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Restores the suppression depth when a [`with_log_suppressed`] closure
+/// returns or panics.
+struct SuppressGuard;
+
+impl Drop for SuppressGuard {
+    fn drop(&mut self) {
+        SUPPRESSED.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Run `f`, dropping all events emitted on the current thread until it
+/// returns. Events on other threads, and spans, are unaffected.
 ///
-/// ## Panics
+/// This applies to the fmt, file, journald and OTLP layers created by this
+/// module, e.g. via [`init_tracing`] or [`build_registry`], but not to layers
+/// added to them by the caller. Calls may be nested.
 ///
-/// This function will panic if a global subscriber has already been set.
+/// ```
+/// # use rust_tracing::utils::tracing::with_log_suppressed;
+/// with_log_suppressed(|| {
+///     tracing::warn!("retrying"); // dropped
+/// });
+/// ```
+pub fn with_log_suppressed<R>(f: impl FnOnce() -> R) -> R {
+    SUPPRESSED.with(|depth| depth.set(depth.get() + 1));
+    let _guard = SuppressGuard;
+    f()
+}
+
+/// Returns false for events emitted while [`with_log_suppressed`] is running
+/// on the current thread.
+pub(crate) fn not_suppressed(meta: &tracing::Metadata<'_>) -> bool {
+    !meta.is_event() || SUPPRESSED.with(|depth| depth.get() == 0)
+}
+
+/// The OpenTelemetry trace and span ids of the current span, if it is
+/// recorded by the OTLP layer.
 ///
-/// [`OtelConfig`]: crate::utils::otlp::OtelConfig
-pub fn init_tracing() -> Option<OtelGuard> {
-    let registry = tracing_subscriber::registry();
-
-    if let Some(cfg) = OtelConfig::load() {
-        let guard = cfg.provider();
-        let registry = registry.with(guard.layer());
-        install_fmt!(registry);
-        Some(guard)
+/// The ids are read from the span's extensions, as the current dispatcher is
+/// not accessible while an event is being formatted.
+#[cfg(feature = "otlp")]
+fn current_trace_ids<S, N>(ctx: &FmtContext<'_, S, N>) -> Option<(String, String)>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    N: for<'writer> FormatFields<'writer> + 'static,
+{
+    use opentelemetry::trace::TraceContextExt;
+
+    let span = ctx.lookup_current()?;
+    let extensions = span.extensions();
+    let data = extensions.get::<tracing_opentelemetry::OtelData>()?;
+    // Only root spans are assigned a trace id, others inherit their parent's.
+    let trace_id = data
+        .builder
+        .trace_id
+        .unwrap_or_else(|| data.parent_cx.span().span_context().trace_id());
+    let span_id = data.builder.span_id?;
+    Some((trace_id.to_string(), span_id.to_string()))
+}
+
+/// Without the `otlp` feature, spans carry no trace ids.
+#[cfg(not(feature = "otlp"))]
+const fn current_trace_ids<S, N>(_ctx: &FmtContext<'_, S, N>) -> Option<(String, String)>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    N: for<'writer> FormatFields<'writer> + 'static,
+{
+    None
+}
+
+/// Event format adding the `trace_id` and `span_id` of the current span, if
+/// enabled. Text lines are prefixed with them, and JSON objects get them as
+/// top-level fields.
+struct TraceIds<F> {
+    inner: F,
+    enabled: bool,
+    json: bool,
+}
+
+impl<S, N, F> FormatEvent<S, N> for TraceIds<F>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    N: for<'writer> FormatFields<'writer> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> core::fmt::Result {
+        let ids = self.enabled.then(|| current_trace_ids(ctx)).flatten();
+        let Some((trace_id, span_id)) = ids else {
+            return self.inner.format_event(ctx, writer, event);
+        };
+        if !self.json {
+            write!(writer, "trace_id={trace_id} span_id={span_id} ")?;
+            return self.inner.format_event(ctx, writer, event);
+        }
+
+        let mut line = String::new();
+        self.inner
+            .format_event(ctx, Writer::new(&mut line), event)?;
+        match line.strip_prefix('{') {
+            Some(rest) => {
+                write!(
+                    writer,
+                    r#"{{"trace_id":"{trace_id}","span_id":"{span_id}",{rest}"#
+                )
+            }
+            None => writer.write_str(&line),
+        }
+    }
+}
+
+/// Apply the output and timestamp formats, span events, thread info and
+/// trace ids to a format layer, and box it.
+fn finish_fmt<W>(
+    layer: FmtLayer<Registry, DefaultFields, Format, W>,
+    format: LogFormat,
+    trace_ids: bool,
+) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    macro_rules! with_ids {
+        ($layer:expr, $json:literal) => {
+            $layer
+                .map_event_format(|inner| {
+                    TraceIds {
+                        inner,
+                        enabled: trace_ids,
+                        json: $json,
+                    }
+                })
+                .boxed()
+        };
+    }
+    macro_rules! finish {
+        ($layer:expr) => {
+            match format {
+                LogFormat::Full => with_ids!($layer, false),
+                LogFormat::Compact => with_ids!($layer.compact(), false),
+                LogFormat::Pretty => with_ids!($layer.pretty(), false),
+                LogFormat::Json => with_ids!($layer.json(), true),
+            }
+        };
+    }
+
+    let time = LogTimeFormat::from_env_var(TRACING_LOG_TIME_FORMAT)
+        .inspect_err(|e| {
+            if matches!(e, FromEnvErr::ParseError(_)) {
+                eprintln!("{e}")
+            }
+        })
+        .unwrap_or_default();
+    let utc = bool::from_env_var(TRACING_LOG_TIME_UTC).unwrap_or(false);
+    let span_events = FmtSpan::from_env_var(TRACING_LOG_SPAN_EVENTS)
+        .inspect_err(|e| {
+            if matches!(e, FromEnvErr::ParseError(_)) {
+                eprintln!("{e}")
+            }
+        })
+        .unwrap_or(FmtSpan::NONE);
+    let layer = layer
+        .with_span_events(span_events)
+        .with_thread_names(bool::from_env_var(TRACING_LOG_THREAD_NAMES).unwrap_or(false))
+        .with_thread_ids(bool::from_env_var(TRACING_LOG_THREAD_IDS).unwrap_or(false));
+
+    match time {
+        LogTimeFormat::System => finish!(layer),
+        LogTimeFormat::Rfc3339 if utc => finish!(layer.with_timer(ChronoUtc::rfc_3339())),
+        LogTimeFormat::Rfc3339 => finish!(layer.with_timer(ChronoLocal::rfc_3339())),
+        LogTimeFormat::Uptime => finish!(layer.with_timer(Uptime::default())),
+        LogTimeFormat::None => finish!(layer.without_time()),
+    }
+}
+
+/// Whether stdout logs should use ANSI colors. `TRACING_LOG_ANSI` takes
+/// precedence if set to a boolean. Otherwise colors are used if stdout is a
+/// terminal and `NO_COLOR` is not set. Invalid values are reported.
+fn stdout_ansi() -> bool {
+    match bool::from_env_var(TRACING_LOG_ANSI) {
+        Ok(ansi) => return ansi,
+        Err(e @ FromEnvErr::ParseError(_)) => eprintln!("{e}"),
+        Err(_) => {}
+    }
+    // Per no-color.org, any non-empty value disables colors.
+    String::from_env_var(NO_COLOR).is_err() && std::io::stdout().is_terminal()
+}
+
+/// The maximum level logged to stdout, i.e. `TRACING_STDOUT_LEVEL`, or `OFF`
+/// if `TRACING_TO_STDOUT` is `false`.
+fn stdout_level() -> LevelFilter {
+    if bool::from_env_var(TRACING_TO_STDOUT).unwrap_or(true) {
+        layer_level(TRACING_STDOUT_LEVEL)
     } else {
-        install_fmt!(registry);
-        None
+        LevelFilter::OFF
+    }
+}
+
+/// The maximum level of a single layer, from the environment variable.
+/// Defaults to `TRACE`, i.e. leaving the log filter alone. Invalid values
+/// are reported.
+fn layer_level(var: &str) -> LevelFilter {
+    LevelFilter::from_env_var(var)
+        .inspect_err(|e| {
+            if matches!(e, FromEnvErr::ParseError(_)) {
+                eprintln!("{e}")
+            }
+        })
+        .unwrap_or(LevelFilter::TRACE)
+}
+
+/// Cap the writer at the level, discarding more verbose lines.
+///
+/// The cap applies at the writer rather than via a per-layer filter, as
+/// per-layer filters of the layers swapped in by [`reinit_tracing`] are not
+/// registered with the subscriber. Events it discards are therefore still
+/// formatted, if the log filter enables them.
+fn capped<W>(writer: W, level: LevelFilter) -> BoxMakeWriter
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    match level.into_level() {
+        Some(level) => BoxMakeWriter::new(writer.with_max_level(level)),
+        None => BoxMakeWriter::new(std::io::sink),
+    }
+}
+
+/// Create a format layer in the given format, based on the
+/// `TRACING_LOG_TIME_*` environment variables, additionally writing to a
+/// rotating file if `TRACING_LOG_DIR` is set. Stdout logs are capped at
+/// [`stdout_level`], and file logs at `TRACING_FILE_LEVEL`. Both are capped at
+/// their writers, as the layers share the log filter, so lines above the caps
+/// are formatted before being discarded. Returns the layer and the file
+/// writer's guard, if any.
+///
+/// Log lines include the trace and span ids if `TRACING_LOG_TRACE_IDS` is
+/// set, defaulting to whether OTLP is enabled.
+fn fmt_layer(format: LogFormat, otlp: bool) -> (BoxedLayer, Option<WorkerGuard>) {
+    let trace_ids = bool::from_env_var(TRACING_LOG_TRACE_IDS).unwrap_or(otlp);
+
+    let stdout = finish_fmt(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(stdout_ansi())
+            .with_writer(capped(std::io::stdout, stdout_level())),
+        format,
+        trace_ids,
+    );
+
+    let Ok(dir) = String::from_env_var(TRACING_LOG_DIR) else {
+        return (stdout, None);
+    };
+    let Some(file_level) = layer_level(TRACING_FILE_LEVEL).into_level() else {
+        return (stdout, None);
+    };
+    let rotation = LogRotation::from_env_var(TRACING_LOG_ROTATION)
+        .inspect_err(|e| {
+            if matches!(e, FromEnvErr::ParseError(_)) {
+                eprintln!("{e}")
+            }
+        })
+        .unwrap_or_default();
+    let appender = match RollingFileAppender::builder()
+        .rotation(rotation.into())
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .build(&dir)
+    {
+        Ok(appender) => appender,
+        Err(err) => {
+            eprintln!("failed to create log file in {dir}: {err}");
+            return (stdout, None);
+        }
+    };
+
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let file = finish_fmt(
+        tracing_subscriber::fmt::layer()
+            .with_writer(writer.with_max_level(file_level))
+            .with_ansi(false),
+        format,
+        trace_ids,
+    );
+
+    (stdout.and_then(file).boxed(), Some(guard))
+}
+
+/// Print a warning to stderr if the guard's layers write logs nowhere, i.e.
+/// stdout logging is off, and neither OTLP, a log file nor journald is
+/// enabled. Returns `true` if the warning was printed.
+fn warn_if_no_sinks(guard: &TracingGuard) -> bool {
+    let stdout = stdout_level() != LevelFilter::OFF
+        && guard
+            .filter
+            .0
+            .with_current(|filter| {
+                <EnvFilter as Layer<Registry>>::max_level_hint(filter) != Some(LevelFilter::OFF)
+            })
+            .unwrap_or(true);
+    #[cfg(feature = "journald")]
+    let stdout = stdout || guard.journald;
+    let blind = !stdout && guard.otel.is_none() && guard.file.is_none();
+    if blind {
+        eprintln!(
+            "no log output is configured: stdout logging is off, and neither OTLP, a log file nor journald is enabled"
+        );
+    }
+    blind
+}
+
+thread_local! {
+    /// The root span, entered on the thread that initialized tracing.
+    static ENTERED_ROOT: RefCell<Option<EnteredSpan>> = const { RefCell::new(None) };
+}
+
+/// The root span of a [`TracingGuard`]. It is entered on the thread that
+/// created it, and exited when dropped on that thread.
+#[derive(Debug)]
+struct RootSpan(Span);
+
+impl RootSpan {
+    /// Enter the span on the current thread.
+    fn enter(span: Span) -> Self {
+        let entered = span.clone().entered();
+        let previous = ENTERED_ROOT.with_borrow_mut(|root| root.replace(entered));
+        drop(previous);
+        Self(span)
+    }
+}
+
+impl Drop for RootSpan {
+    fn drop(&mut self) {
+        let entered = ENTERED_ROOT
+            .try_with(|root| {
+                root.borrow_mut()
+                    .take_if(|entered| entered.id() == self.0.id())
+            })
+            .ok()
+            .flatten();
+        drop(entered);
     }
 }
 
-/// Init tracing with journald, returning an optional guard for the OTEL provider.
+/// Create and enter the root span, if `TRACING_ROOT_SPAN_NAME` is set. Span
+/// names are static, so the span is named `root`, and carries the configured
+/// name as its `name` field. It is exported to OTLP under the configured
+/// name.
+fn root_span() -> Option<RootSpan> {
+    let name = String::from_env_var(TRACING_ROOT_SPAN_NAME).ok()?;
+    Some(RootSpan::enter(
+        tracing::info_span!("root", name = %name, otel.name = %name),
+    ))
+}
+
+/// Create the reloadable layers from the overrides and the environment,
+/// returning the layers, their reload handles, and the guard.
+///
+/// The layers are collected in a [`Vec`] rather than combined with
+/// [`Layer::and_then`], as the latter only reports the max level hint of the
+/// outermost layer when the subscriber is the [`Registry`].
+fn layers(overrides: Overrides) -> (Vec<BoxedLayer>, Reload, TracingGuard) {
+    let otel = overrides.otel_guard();
+    let (fmt, file) = fmt_layer(overrides.format(), otel.is_some());
+
+    let (fmt, fmt_handle) = reload::Layer::new(fmt);
+    let (filter, filter_handle) = reload::Layer::new(overrides.filter());
+    let fmt = fmt
+        .with_filter(filter.and(filter_fn(not_suppressed).with_max_level_hint(LevelFilter::TRACE)));
+
+    #[cfg(feature = "sentry")]
+    let (sentry_layer, sentry) = super::sentry::layer().unzip();
+
+    // Not subject to the log filter, so that e.g. journald may keep `DEBUG`
+    // while stdout logs `INFO`.
+    #[cfg(feature = "journald")]
+    let journald = overrides
+        .journald()
+        .then(|| journald_layer(tracing_journald::layer()))
+        .flatten();
+
+    let guard = TracingGuard {
+        root: None,
+        otel,
+        #[cfg(feature = "sentry")]
+        sentry,
+        file,
+        #[cfg(feature = "journald")]
+        journald: journald.is_some(),
+        filter: ReloadHandle(filter_handle.clone()),
+    };
+
+    #[cfg(feature = "otlp")]
+    let (otel, otel_handle) = guard.otel().map(OtelReload::layers).unzip();
+
+    let handles = Reload {
+        fmt: fmt_handle,
+        filter: filter_handle,
+        #[cfg(feature = "otlp")]
+        otel: otel_handle,
+        overrides,
+    };
+
+    #[cfg(feature = "otlp")]
+    let layers = otel.into_iter().flatten().chain([fmt.boxed()]);
+    #[cfg(not(feature = "otlp"))]
+    let layers = [fmt.boxed()].into_iter();
+    #[cfg(feature = "sentry")]
+    let layers = layers.chain(sentry_layer);
+    #[cfg(feature = "journald")]
+    let layers = layers.chain(journald);
+    let layers = layers.collect();
+
+    (layers, handles, guard)
+}
+
+/// Create the fmt, file and OTLP layers, without installing them, returning
+/// the layers and their guard.
+///
+/// This allows composing a subscriber with additional layers:
+///
+/// ```no_run
+/// # use rust_tracing::utils::tracing::tracing_layers;
+/// use tracing_subscriber::{
+///     layer::SubscriberExt,
+///     util::SubscriberInitExt,
+/// };
+///
+/// let (layers, guard) = tracing_layers();
+/// tracing_subscriber::registry()
+///     .with(layers)
+///     // .with(my_layer)
+///     .init();
+/// ```
+///
+/// Layers created by this function are not replaced by [`reinit_tracing`].
+/// As the subscriber is not yet installed, the guard never holds a root span.
+///
+/// ## Env Reads
+///
+/// As [`init_tracing`], except `TRACING_ROOT_SPAN_NAME`.
+pub fn tracing_layers() -> (impl Layer<Registry>, TracingGuard) {
+    let (layers, _, guard) = layers(Overrides::default());
+    (layers, guard)
+}
+
+/// Create a [`Registry`] with the fmt, file and OTLP layers, without
+/// installing it globally, returning the subscriber and its guard. Further
+/// layers may be added via [`SubscriberExt::with`].
+///
+/// Layers created by this function are not replaced by [`reinit_tracing`].
+/// As the subscriber is not installed, the guard never holds a root span.
+///
+/// ## Env Reads
+///
+/// As [`init_tracing`], except `TRACING_ROOT_SPAN_NAME`.
+pub fn build_registry() -> (
+    impl tracing::Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+    TracingGuard,
+) {
+    let (layers, guard) = tracing_layers();
+    (tracing_subscriber::registry().with(layers), guard)
+}
+
+/// Init tracing, returning a guard for the OTEL provider and log file.
 ///
 /// If the OTEL environment variables are not set, this function will
 /// initialize a basic tracing subscriber with a `fmt` layer. If the
 /// environment variables are set, it will initialize the OTEL provider
-/// with the specified configuration, as well as the `fmt` layer.
+/// with the specified configuration, as well as the `fmt` layer. The OTLP
+/// configuration is then logged, see [`FromEnv::log_effective`].
+///
+/// The installed layers may later be replaced via [`reinit_tracing`].
 ///
 /// ## Env Reads
 ///
-/// - `TRACING_LOG_JSON` - If set, will enable JSON logging.
-/// - As [`OtelConfig`] documentation for env var information.
+/// - `TRACING_FILTER` - The log filter, as [`EnvFilter`] directives, e.g.
+///   `mycrate=debug,hyper=warn`. Takes precedence over `RUST_LOG`, which is
+///   used if it is unset or invalid. If neither is set, defaults to `info`.
+///   Unless `OTEL_FILTER` is set, the same directives, if any, also filter
+///   the spans and logs exported via OTLP, alongside `OTEL_LEVEL`.
+/// - `TRACING_STDOUT_LEVEL` - The maximum level logged to stdout, e.g.
+///   `info`, or `off` to log only to the file and OTLP. Applies on top of the
+///   log filter, so the stdout, file and OTLP levels may differ, e.g.
+///   `RUST_LOG=debug`, `TRACING_STDOUT_LEVEL=info` and `OTEL_LEVEL=WARN`.
+///   Lines above the level are discarded after formatting, so prefer the log
+///   filter to avoid the cost of formatting them at all. Defaults to no
+///   additional limit.
+/// - `TRACING_TO_STDOUT` - `true` or `false`, whether to log to stdout, e.g.
+///   `false` to log only to the file, journald and OTLP. Defaults to `true`.
+/// - `TRACING_LOG_FORMAT` - The log format, one of `full`, `compact`,
+///   `pretty` or `json`. Defaults to `json` if `TRACING_LOG_JSON` is true and
+///   `full` if it is false. If neither is set, defaults to `pretty` when
+///   stdout is a terminal and `json` otherwise.
+/// - `TRACING_LOG_JSON` - If set, will enable JSON logging, unless
+///   `TRACING_LOG_FORMAT` is set.
+/// - `TRACING_LOG_DIR` - If set, logs are additionally written to a rotating
+///   file in this directory.
+/// - `TRACING_FILE_LEVEL` - The maximum level logged to the file, on top of
+///   the log filter, as `TRACING_STDOUT_LEVEL`. As for stdout, lines above
+///   the level are discarded after formatting. Defaults to no additional
+///   limit.
+/// - `TRACING_TO_JOURNALD` - With the `journald` feature, if `true`, logs are
+///   additionally sent to journald, as by `init_tracing_with_journald`, which
+///   documents the journald env vars. Defaults to `false`.
+/// - `TRACING_LOG_ROTATION` - How often to rotate the log file, one of
+///   `daily`, `hourly` or `never`. Defaults to `daily`.
+/// - `TRACING_LOG_TIME_FORMAT` - The log timestamp format, one of `system`,
+///   `rfc3339`, `uptime` or `none`. Defaults to `system`, the
+///   `tracing_subscriber` default.
+/// - `TRACING_LOG_TIME_UTC` - If set, `rfc3339` timestamps are in UTC rather
+///   than local time.
+/// - `TRACING_LOG_SPAN_EVENTS` - Comma-separated span lifecycle events to
+///   log, from `none`, `new`, `enter`, `exit`, `close`, `active` and `full`.
+///   `close` events include the span's busy and idle time. Defaults to `none`.
+/// - `TRACING_LOG_THREAD_NAMES` - If set, log lines include the name of the
+///   emitting thread.
+/// - `TRACING_LOG_THREAD_IDS` - If set, log lines include the id of the
+///   emitting thread.
+/// - `TRACING_LOG_TRACE_IDS` - `true` or `false`, whether log lines include
+///   the OpenTelemetry `trace_id` and `span_id` of the current span, to
+///   correlate logs with traces. Text lines are prefixed with them, and JSON
+///   lines carry them as top-level fields. Defaults to `true` if OTLP is
+///   enabled.
+/// - `TRACING_LOG_ANSI` - `true` or `false`, whether to color stdout logs.
+///   By default, colors are used if stdout is a terminal and `NO_COLOR` is
+///   not set.
+/// - `TRACING_ROOT_SPAN_NAME` - If set, an `INFO` span with this name is
+///   entered for the lifetime of the returned guard. See [`TracingGuard`].
+/// - `SENTRY_DSN` - With the `sentry` feature, if set, `ERROR` and `WARN`
+///   events are reported to Sentry, with preceding `INFO` events as
+///   breadcrumbs. The Sentry client additionally reads `SENTRY_ENVIRONMENT`
+///   and `SENTRY_RELEASE`. The Sentry layer is not subject to the log filter,
+///   and is not replaced by [`reinit_tracing`].
+/// - As [`OtelConfig`] documentation for env var information. Without the
+///   `otlp` feature, OTLP is never enabled and [`TracingGuard::otel`] returns
+///   [`None`]. If the OTLP exporters fail to build, the error is printed and
+///   OTLP is disabled.
+///
+/// If stdout logging is off, e.g. via `RUST_LOG=off` or
+/// `TRACING_TO_STDOUT=false`, and neither OTLP, a log file nor journald is
+/// enabled, a warning that no log output is configured is printed to
+/// stderr.
+///
+/// ## Repeated calls
+///
+/// The first caller wins. If tracing was already initialized by this crate,
+/// e.g. by another library in the same process, nothing is read or
+/// installed, and the returned guard holds nothing but the
+/// [`ReloadHandle`] of the installed tracing. Dropping it has no effect.
+/// Concurrent calls wait for the first to finish.
 ///
 /// ## Panics
 ///
-/// This function will panic if a global subscriber has already been set.
+/// This function will panic if a global subscriber has already been set
+/// other than by this crate. See [`try_init_tracing`] for a non-panicking
+/// version.
 ///
 /// [`OtelConfig`]: crate::utils::otlp::OtelConfig
+pub fn init_tracing() -> TracingGuard {
+    try_init_tracing().expect("failed to install tracing subscriber")
+}
+
+/// Init tracing, returning a guard for the OTEL provider and log file, or an
+/// error if a global subscriber was already installed other than by this
+/// crate.
+///
+/// If an error is returned, the OTEL provider and log file, if any, are shut
+/// down.
+///
+/// See [`init_tracing`] for more information.
+pub fn try_init_tracing() -> Result<TracingGuard, TracingInitError> {
+    try_init_tracing_with(Overrides::default())
+}
+
+/// As [`try_init_tracing`], with settings taking precedence over the
+/// environment.
+pub(crate) fn try_init_tracing_with(
+    overrides: Overrides,
+) -> Result<TracingGuard, TracingInitError> {
+    let _lock = init_lock();
+    try_init_tracing_locked(overrides)
+}
+
+/// As [`try_init_tracing_with`], for callers already holding [`init_lock`],
+/// e.g. to initialize metrics under the same lock.
+pub(crate) fn try_init_tracing_locked(
+    overrides: Overrides,
+) -> Result<TracingGuard, TracingInitError> {
+    if let Some(guard) = TracingGuard::existing() {
+        return Ok(guard);
+    }
+    let (layers, handles, guard) = layers(overrides);
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .map_err(TracingInitError)?;
+    let _ = RELOAD.set(handles);
+    warn_if_no_sinks(&guard);
+    Ok(TracingGuard {
+        root: root_span(),
+        ..guard
+    })
+}
+
+/// Init tracing with journald, returning a guard for the OTEL provider and
+/// log file. This is [`init_tracing`] with `TRACING_TO_JOURNALD` forced to
+/// `true`.
+///
+/// The installed layers, other than the journald layer, may later be
+/// replaced via [`reinit_tracing`].
+///
+/// ## Env Reads
+///
+/// As [`init_tracing`], and:
+///
+/// - `TRACING_JOURNALD_LEVEL` - The maximum level logged to journald, e.g.
+///   `warn`. Unlike the other layers, the journald layer is not subject to
+///   the log filter. Defaults to `TRACE`.
+/// - `TRACING_JOURNALD_IDENTIFIER` - The syslog identifier of the entries,
+///   e.g. to filter them with `journalctl -t`. Defaults to the file name of
+///   the executable.
+/// - `TRACING_JOURNALD_FIELD_PREFIX` - The prefix of the names of the event
+///   fields, other than `message`. If empty, the names are not prefixed.
+///   Defaults to `F`.
+///
+/// Repeated calls are no-ops, as for [`init_tracing`].
+///
+/// If journald is unavailable, e.g. in a container or on macOS, a warning is
+/// printed to stderr, and the other layers are installed without the
+/// journald layer.
+///
+/// ## Panics
+///
+/// This function will panic if a global subscriber has already been set
+/// other than by this crate.
 #[cfg(feature = "journald")]
-pub fn init_tracing_with_journald() -> Option<OtelGuard> {
-    let registry = tracing_subscriber::registry()
-        .with(tracing_journald::layer().expect("failed to create layer"));
-
-    if let Some(cfg) = OtelConfig::load() {
-        let guard = cfg.provider();
-        let registry = registry.with(guard.layer());
-        install_fmt!(registry);
-        Some(guard)
-    } else {
-        install_fmt!(registry);
-        None
+pub fn init_tracing_with_journald() -> TracingGuard {
+    try_init_tracing_with(Overrides {
+        journald: true,
+        ..Default::default()
+    })
+    .expect("failed to install tracing subscriber")
+}
+
+/// Configure the journald layer, as returned by [`tracing_journald::layer`],
+/// with the syslog identifier, field prefix and level from the environment,
+/// if set. Returns `None`, printing a warning, if the journald socket could
+/// not be reached.
+#[cfg(feature = "journald")]
+fn journald_layer(layer: std::io::Result<tracing_journald::Layer>) -> Option<BoxedLayer> {
+    let mut layer = layer
+        .inspect_err(|err| eprintln!("journald is unavailable, not logging to it: {err}"))
+        .ok()?;
+    if let Ok(identifier) = String::from_env_var(TRACING_JOURNALD_IDENTIFIER) {
+        layer = layer.with_syslog_identifier(identifier);
+    }
+    let layer = match String::from_env_var(TRACING_JOURNALD_FIELD_PREFIX) {
+        Ok(prefix) => layer.with_field_prefix(Some(prefix)),
+        Err(FromEnvErr::Empty(_)) => layer.with_field_prefix(None),
+        Err(_) => layer,
+    };
+    Some(
+        layer
+            .with_filter(layer_level(TRACING_JOURNALD_LEVEL).and(filter_fn(not_suppressed)))
+            .boxed(),
+    )
+}
+
+/// Re-read the tracing configuration from the environment, and swap the
+/// layers installed by [`init_tracing`] for freshly configured ones.
+///
+/// The new OTEL provider and log file, if any, are installed before the old
+/// guard is dropped, so the old provider and log file are flushed and shut
+/// down without losing spans or lines. Returns the new guard, which should
+/// replace the old one.
+///
+/// Settings made via [`TracingBuilder`] keep taking precedence over the
+/// environment.
+///
+/// If OTLP was disabled when tracing was initialized, its layers were not
+/// installed, so it stays disabled, and a warning is printed if the new
+/// configuration enables it. If it was enabled, it may be disabled and
+/// enabled again.
+///
+/// If tracing has not been initialized via [`init_tracing`] or
+/// `init_tracing_with_journald`, this drops the guard and calls
+/// [`init_tracing`].
+///
+/// ## Env Reads
+///
+/// As [`init_tracing`].
+///
+/// ## Panics
+///
+/// This function will panic if tracing has not been initialized and a global
+/// subscriber has already been set.
+///
+/// [`TracingBuilder`]: crate::TracingBuilder
+pub fn reinit_tracing(mut guard: TracingGuard) -> TracingGuard {
+    let Some(handles) = RELOAD.get() else {
+        drop(guard);
+        return init_tracing();
+    };
+
+    // Close the old root span while the old provider is still installed.
+    drop(guard.root.take());
+
+    #[cfg(feature = "otlp")]
+    let otel = match handles.otel {
+        Some(_) => handles.overrides.otel_guard(),
+        None => {
+            if handles.overrides.otel_guard().is_some() {
+                eprintln!("OTLP was disabled when tracing was initialized, restart to enable it");
+            }
+            None
+        }
+    };
+    #[cfg(not(feature = "otlp"))]
+    let otel = handles.overrides.otel_guard();
+    let (fmt, file) = fmt_layer(handles.overrides.format(), otel.is_some());
+    let mut new_guard = TracingGuard {
+        root: None,
+        otel,
+        // The Sentry layer is not replaced, so neither is its client.
+        #[cfg(feature = "sentry")]
+        sentry: guard.sentry.take(),
+        file,
+        // Neither is the journald layer.
+        #[cfg(feature = "journald")]
+        journald: guard.journald,
+        filter: ReloadHandle(handles.filter.clone()),
+    };
+
+    #[cfg(feature = "otlp")]
+    if let Some(otel) = &handles.otel {
+        otel.reload(new_guard.otel())
+            .expect("global subscriber is never dropped");
+    }
+    handles
+        .fmt
+        .reload(fmt)
+        .expect("global subscriber is never dropped");
+    handles
+        .filter
+        .reload(handles.overrides.filter())
+        .expect("global subscriber is never dropped");
+
+    new_guard.root = root_span();
+    drop(guard);
+    new_guard
+}
+
+/// The env vars read by [`init_tracing`], other than the OTLP ones.
+pub(crate) fn inventory() -> Vec<&'static EnvItemInfo> {
+    vec![
+        &EnvItemInfo {
+            var: TRACING_FILTER,
+            description: "Log filter directives, e.g. `mycrate=debug,hyper=warn`. Takes precedence over RUST_LOG",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: RUST_LOG,
+            description: "Log filter directives, used if TRACING_FILTER is unset. Defaults to `info`",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_STDOUT_LEVEL,
+            description: "Maximum level logged to stdout, on top of the log filter",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_TO_STDOUT,
+            description: "Whether to log to stdout, boolean, defaults to true",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_FORMAT,
+            description: "Log format, one of `full`, `compact`, `pretty` or `json`",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_JSON,
+            description: "Whether to log JSON, unless TRACING_LOG_FORMAT is set",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_DIR,
+            description: "Directory to additionally write rotating log files to",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_FILE_LEVEL,
+            description: "Maximum level logged to the log file, on top of the log filter",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_ROTATION,
+            description: "Log file rotation, one of `daily`, `hourly` or `never`, defaults to `daily`",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_TIME_FORMAT,
+            description: "Log timestamp format, one of `system`, `rfc3339`, `uptime` or `none`",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_TIME_UTC,
+            description: "Whether `rfc3339` timestamps are in UTC",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_SPAN_EVENTS,
+            description: "Comma-separated span lifecycle events to log, defaults to `none`",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_THREAD_NAMES,
+            description: "Whether log lines include the thread name",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_THREAD_IDS,
+            description: "Whether log lines include the thread id",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_TRACE_IDS,
+            description: "Whether log lines include the trace and span ids, defaults to true if OTLP is enabled",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_LOG_ANSI,
+            description: "Whether to color stdout logs, defaults to true on a terminal unless NO_COLOR is set",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_ROOT_SPAN_NAME,
+            description: "Name of a root span entered for the lifetime of the guard",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        #[cfg(feature = "journald")]
+        &EnvItemInfo {
+            var: TRACING_TO_JOURNALD,
+            description: "Whether to additionally log to journald, boolean, defaults to false",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        #[cfg(feature = "journald")]
+        &EnvItemInfo {
+            var: TRACING_JOURNALD_LEVEL,
+            description: "Maximum level logged to journald, defaults to TRACE",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        #[cfg(feature = "journald")]
+        &EnvItemInfo {
+            var: TRACING_JOURNALD_IDENTIFIER,
+            description: "Syslog identifier of the journald entries, defaults to the executable name",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        #[cfg(feature = "journald")]
+        &EnvItemInfo {
+            var: TRACING_JOURNALD_FIELD_PREFIX,
+            description: "Prefix of the journald field names, none if empty, defaults to F",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+        &EnvItemInfo {
+            var: TRACING_WARN_UNKNOWN_VARS,
+            description: "Whether to warn about unknown TRACING_ and OTEL_ env vars at startup, defaults to false",
+            optional: true,
+            secret: false,
+            aliases: &[],
+        },
+    ]
+}
+
+/// Warn about set `TRACING_` and `OTEL_` env vars that are not read by this
+/// crate, e.g. typos, if `TRACING_WARN_UNKNOWN_VARS` is set. `OTEL_` vars are
+/// only checked with the `otlp` feature. See [`warn_unknown_vars`].
+///
+/// This is opt-in, as the OpenTelemetry SDK reads further standard `OTEL_`
+/// vars, which are reported too.
+pub(crate) fn warn_unknown_env_vars() {
+    if !bool::from_env_var(TRACING_WARN_UNKNOWN_VARS).unwrap_or(false) {
+        return;
+    }
+    let mut inventory = inventory();
+    inventory.extend(MetricsConfig::inventory());
+    warn_unknown_vars("TRACING_", &inventory);
+    #[cfg(feature = "otlp")]
+    warn_unknown_vars("OTEL_", &OtelConfig::inventory());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(all(feature = "signal", unix))]
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn shutdown_on_signal() {
+        let signal = shutdown_signal();
+        signal_hook::low_level::raise(signal_hook::consts::SIGTERM).unwrap();
+        assert_eq!(signal.await, 143);
+    }
+
+    #[test]
+    fn guard_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        fn check(guard: TracingGuard) {
+            assert_send(&guard);
+            assert_send(&guard.shutdown());
+        }
+        let _ = check;
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn build_registry_accepts_extra_layers() {
+        use std::sync::{
+            Arc,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+        };
+
+        struct Count(Arc<AtomicUsize>);
+
+        impl<S: tracing::Subscriber> Layer<S> for Count {
+            fn on_event(
+                &self,
+                _event: &tracing::Event<'_>,
+                _ctx: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let (registry, _guard) = build_registry();
+        let subscriber = registry.with(Count(Arc::clone(&count)));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::error!("captured");
+        });
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn file_logging() {
+        let dir = std::env::temp_dir().join(format!("rust-tracing-{}", std::process::id()));
+        unsafe {
+            std::env::set_var(TRACING_LOG_DIR, &dir);
+            std::env::set_var(TRACING_LOG_ROTATION, "never");
+            std::env::set_var(TRACING_FILE_LEVEL, "warn");
+        }
+
+        let (registry, guard) = build_registry();
+        assert!(guard.is_file_logging());
+        tracing::subscriber::with_default(registry, || {
+            tracing::error!("written to file");
+            tracing::info!("below the file level");
+        });
+        drop(guard);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::remove_var(TRACING_LOG_ROTATION);
+            std::env::remove_var(TRACING_FILE_LEVEL);
+        }
+        let contents = std::fs::read_to_string(dir.join("tracing.log")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(contents.contains("written to file"));
+        assert!(!contents.contains("below the file level"));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn no_sinks_warning() {
+        let dir = std::env::temp_dir().join(format!("rust-tracing-sinks-{}", std::process::id()));
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::set_var("RUST_LOG", "off");
+        }
+        let (_layers, guard) = tracing_layers();
+        assert!(warn_if_no_sinks(&guard));
+
+        unsafe { std::env::set_var(TRACING_LOG_DIR, &dir) };
+        let (_layers, guard) = tracing_layers();
+        assert!(!warn_if_no_sinks(&guard));
+        drop(guard);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::set_var("RUST_LOG", "info");
+        }
+        let (_layers, guard) = tracing_layers();
+        assert!(!warn_if_no_sinks(&guard));
+
+        unsafe { std::env::set_var(TRACING_STDOUT_LEVEL, "off") };
+        let (_layers, guard) = tracing_layers();
+        assert!(warn_if_no_sinks(&guard));
+
+        unsafe {
+            std::env::remove_var(TRACING_STDOUT_LEVEL);
+            std::env::set_var(TRACING_TO_STDOUT, "false");
+        }
+        let (_layers, guard) = tracing_layers();
+        assert!(warn_if_no_sinks(&guard));
+
+        unsafe {
+            std::env::remove_var(TRACING_TO_STDOUT);
+            std::env::remove_var("RUST_LOG");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A writer collecting the lines written to it.
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self {
+            self.clone()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "journald")]
+    fn journald_unavailable() {
+        let unavailable = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(journald_layer(Err(unavailable)).is_none());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn capped_writer() {
+        let levels = [
+            LevelFilter::OFF,
+            LevelFilter::ERROR,
+            LevelFilter::WARN,
+            LevelFilter::INFO,
+            LevelFilter::DEBUG,
+            LevelFilter::TRACE,
+        ];
+        for cap in levels {
+            let buffer = Buffer::default();
+            let layer = finish_fmt(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(capped(buffer.clone(), cap)),
+                LogFormat::Compact,
+                false,
+            );
+            let subscriber = tracing_subscriber::registry().with(layer);
+            tracing::subscriber::with_default(subscriber, || {
+                tracing::error!("at-error");
+                tracing::warn!("at-warn");
+                tracing::info!("at-info");
+                tracing::debug!("at-debug");
+                tracing::trace!("at-trace");
+            });
+
+            let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+            for level in &levels[1..] {
+                let line = format!("at-{}", level.to_string().to_lowercase());
+                assert_eq!(
+                    written.contains(&line),
+                    *level <= cap,
+                    "cap {cap}, level {level}: {written}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn stdout_level_env() {
+        unsafe { std::env::remove_var(TRACING_STDOUT_LEVEL) };
+        assert_eq!(layer_level(TRACING_STDOUT_LEVEL), LevelFilter::TRACE);
+
+        unsafe { std::env::set_var(TRACING_STDOUT_LEVEL, "warn") };
+        assert_eq!(layer_level(TRACING_STDOUT_LEVEL), LevelFilter::WARN);
+
+        unsafe { std::env::set_var(TRACING_STDOUT_LEVEL, "loud") };
+        assert_eq!(layer_level(TRACING_STDOUT_LEVEL), LevelFilter::TRACE);
+
+        unsafe { std::env::remove_var(TRACING_STDOUT_LEVEL) };
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn reload_handle_changes_filter() {
+        unsafe {
+            std::env::set_var("RUST_LOG", "error");
+        }
+        let (registry, guard) = build_registry();
+        unsafe {
+            std::env::remove_var("RUST_LOG");
+        }
+
+        let handle = guard.reload_handle().clone();
+        tracing::subscriber::with_default(registry, || {
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+
+            handle.reload("debug").unwrap();
+            assert!(tracing::enabled!(tracing::Level::DEBUG));
+
+            let err = handle.reload("info,my_crate=bogus").unwrap_err();
+            assert!(matches!(err, ReloadError::Filter(_)));
+            assert!(tracing::enabled!(tracing::Level::DEBUG));
+        });
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn suppressed_events_are_dropped() {
+        let dir =
+            std::env::temp_dir().join(format!("rust-tracing-suppress-{}", std::process::id()));
+        unsafe {
+            std::env::set_var(TRACING_LOG_DIR, &dir);
+            std::env::set_var(TRACING_LOG_ROTATION, "never");
+        }
+
+        let (registry, guard) = build_registry();
+        tracing::subscriber::with_default(registry, || {
+            tracing::error!("before");
+            let value = with_log_suppressed(|| {
+                tracing::error!("muted");
+                with_log_suppressed(|| tracing::error!("nested"));
+                tracing::error!("still muted");
+                7
+            });
+            assert_eq!(value, 7);
+            tracing::error!("after");
+
+            let _ = std::panic::catch_unwind(|| with_log_suppressed(|| panic!("unwound")));
+            tracing::error!("after panic");
+        });
+        drop(guard);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::remove_var(TRACING_LOG_ROTATION);
+        }
+        let contents = std::fs::read_to_string(dir.join("tracing.log")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(contents.contains("before"), "{contents}");
+        assert!(contents.contains("after"), "{contents}");
+        assert!(contents.contains("after panic"), "{contents}");
+        assert!(!contents.contains("muted"), "{contents}");
+        assert!(!contents.contains("nested"), "{contents}");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn events_nested_under_root_span() {
+        use std::sync::{
+            Arc,
+            Mutex,
+        };
+
+        struct Parents(Arc<Mutex<Vec<Option<&'static str>>>>);
+
+        impl<S> Layer<S> for Parents
+        where
+            S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+        {
+            fn on_event(
+                &self,
+                event: &tracing::Event<'_>,
+                ctx: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                let parent = ctx.event_span(event).map(|span| span.name());
+                self.0.lock().unwrap().push(parent);
+            }
+        }
+
+        unsafe {
+            std::env::set_var(TRACING_ROOT_SPAN_NAME, "my-service");
+        }
+        let parents = Arc::new(Mutex::new(vec![]));
+        let (registry, _guard) = build_registry();
+        let subscriber = registry.with(Parents(Arc::clone(&parents)));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let root = root_span().unwrap();
+            assert_eq!(root.0.metadata().unwrap().name(), "root");
+            assert!(root.0.metadata().unwrap().fields().field("name").is_some());
+            tracing::error!("nested");
+            drop(root);
+            tracing::error!("not nested");
+        });
+        unsafe {
+            std::env::remove_var(TRACING_ROOT_SPAN_NAME);
+        }
+
+        assert_eq!(*parents.lock().unwrap(), vec![Some("root"), None]);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn log_time_format() {
+        let dir = std::env::temp_dir().join(format!("rust-tracing-time-{}", std::process::id()));
+        unsafe {
+            std::env::set_var(TRACING_LOG_DIR, &dir);
+            std::env::set_var(TRACING_LOG_ROTATION, "never");
+            std::env::set_var(TRACING_LOG_TIME_FORMAT, "rfc3339");
+            std::env::set_var(TRACING_LOG_TIME_UTC, "true");
+            std::env::set_var(TRACING_LOG_FORMAT, "full");
+        }
+
+        let (registry, guard) = build_registry();
+        tracing::subscriber::with_default(registry, || {
+            tracing::error!("timestamped");
+        });
+        drop(guard);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::remove_var(TRACING_LOG_ROTATION);
+            std::env::remove_var(TRACING_LOG_TIME_FORMAT);
+            std::env::remove_var(TRACING_LOG_TIME_UTC);
+            std::env::remove_var(TRACING_LOG_FORMAT);
+        }
+        let contents = std::fs::read_to_string(dir.join("tracing.log")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // e.g. `2025-01-01T00:00:00.000000+00:00  ERROR ...`
+        let timestamp = contents.split_whitespace().next().unwrap();
+        assert!(timestamp.ends_with("+00:00"), "{contents}");
+        assert_eq!(timestamp.as_bytes()[10], b'T');
+
+        assert_eq!("uptime".parse(), Ok(LogTimeFormat::Uptime));
+        assert_eq!("None".parse(), Ok(LogTimeFormat::None));
+        assert_eq!(
+            "iso".parse::<LogTimeFormat>().unwrap_err().to_string(),
+            "invalid log time format: iso"
+        );
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn ansi_env() {
+        unsafe {
+            std::env::set_var(NO_COLOR, "1");
+            std::env::remove_var(TRACING_LOG_ANSI);
+        }
+        assert!(!stdout_ansi());
+
+        unsafe {
+            std::env::set_var(TRACING_LOG_ANSI, "true");
+        }
+        assert!(stdout_ansi());
+        unsafe {
+            std::env::set_var(TRACING_LOG_ANSI, "Yes");
+        }
+        assert!(stdout_ansi());
+        // Invalid values fall back to `NO_COLOR`.
+        unsafe {
+            std::env::set_var(TRACING_LOG_ANSI, "colorful");
+        }
+        assert!(!stdout_ansi());
+
+        unsafe {
+            std::env::remove_var(NO_COLOR);
+            std::env::set_var(TRACING_LOG_ANSI, "false");
+        }
+        assert!(!stdout_ansi());
+        unsafe {
+            std::env::set_var(TRACING_LOG_ANSI, "0");
+        }
+        assert!(!stdout_ansi());
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_ANSI);
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn log_format_env() {
+        unsafe {
+            std::env::remove_var(TRACING_LOG_FORMAT);
+            std::env::remove_var(TRACING_LOG_JSON);
+        }
+        assert_eq!(LogFormat::from_env_or_detect(true), LogFormat::Pretty);
+        assert_eq!(LogFormat::from_env_or_detect(false), LogFormat::Json);
+
+        unsafe {
+            std::env::set_var(TRACING_LOG_JSON, "false");
+        }
+        assert_eq!(LogFormat::from_env_or_detect(false), LogFormat::Full);
+
+        unsafe {
+            std::env::set_var(TRACING_LOG_JSON, "1");
+        }
+        assert_eq!(LogFormat::from_env_or_detect(true), LogFormat::Json);
+
+        unsafe {
+            std::env::set_var(TRACING_LOG_FORMAT, "pretty");
+        }
+        assert_eq!(LogFormat::from_env(), LogFormat::Pretty);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_JSON);
+            std::env::set_var(TRACING_LOG_FORMAT, "Compact");
+        }
+        assert_eq!(LogFormat::from_env(), LogFormat::Compact);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_FORMAT);
+        }
+        assert_eq!(
+            "yaml".parse::<LogFormat>().unwrap_err().to_string(),
+            "invalid log format: yaml"
+        );
+    }
+
+    #[test]
+    #[serial_test::serial]
+    // The OTLP overrides are absent without the `otlp` feature.
+    #[allow(clippy::needless_update)]
+    fn overrides_take_precedence() {
+        unsafe {
+            std::env::set_var(TRACING_LOG_FORMAT, "pretty");
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        let overrides = Overrides {
+            format: Some(LogFormat::Json),
+            filter: Some("warn".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(overrides.format(), LogFormat::Json);
+        assert_eq!(overrides.filter().to_string(), "warn");
+
+        let invalid = Overrides {
+            filter: "=notalevel".parse().ok(),
+            ..Default::default()
+        };
+        assert_eq!(invalid.format(), LogFormat::Pretty);
+        assert_eq!(invalid.filter().to_string(), "trace");
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_FORMAT);
+            std::env::remove_var("RUST_LOG");
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn tracing_filter_env() {
+        unsafe {
+            std::env::set_var(TRACING_FILTER, "mycrate=debug");
+            std::env::set_var("RUST_LOG", "trace");
+        }
+        assert_eq!(Overrides::default().filter().to_string(), "mycrate=debug");
+
+        unsafe { std::env::set_var(TRACING_FILTER, "mycrate=notalevel") };
+        assert_eq!(Overrides::default().filter().to_string(), "trace");
+
+        unsafe {
+            std::env::remove_var(TRACING_FILTER);
+            std::env::remove_var("RUST_LOG");
+        }
+        assert_eq!(Overrides::default().directives(), None);
+        assert_eq!(Overrides::default().filter().to_string(), "info");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn span_events_env() {
+        unsafe {
+            std::env::set_var(TRACING_LOG_SPAN_EVENTS, "new, close");
+        }
+        assert_eq!(
+            FmtSpan::from_env_var(TRACING_LOG_SPAN_EVENTS).unwrap(),
+            FmtSpan::NEW | FmtSpan::CLOSE
+        );
+
+        unsafe {
+            std::env::set_var(TRACING_LOG_SPAN_EVENTS, "full");
+        }
+        assert_eq!(
+            FmtSpan::from_env_var(TRACING_LOG_SPAN_EVENTS).unwrap(),
+            FmtSpan::FULL
+        );
+
+        unsafe {
+            std::env::set_var(TRACING_LOG_SPAN_EVENTS, "new,open");
+        }
+        assert_eq!(
+            FmtSpan::from_env_var(TRACING_LOG_SPAN_EVENTS).unwrap_err(),
+            FromEnvErr::parse_error(SpanEventsParseError("open".to_owned()))
+        );
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_SPAN_EVENTS);
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn log_thread_info() {
+        let dir = std::env::temp_dir().join(format!("rust-tracing-thread-{}", std::process::id()));
+        unsafe {
+            std::env::set_var(TRACING_LOG_DIR, &dir);
+            std::env::set_var(TRACING_LOG_ROTATION, "never");
+            std::env::set_var(TRACING_LOG_THREAD_NAMES, "1");
+            std::env::set_var(TRACING_LOG_THREAD_IDS, "1");
+        }
+
+        let (registry, guard) = build_registry();
+        let dispatch = tracing::Dispatch::new(registry);
+        std::thread::Builder::new()
+            .name("worker-7".to_owned())
+            .spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || tracing::error!("from worker"));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        drop(guard);
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::remove_var(TRACING_LOG_ROTATION);
+            std::env::remove_var(TRACING_LOG_THREAD_NAMES);
+            std::env::remove_var(TRACING_LOG_THREAD_IDS);
+        }
+        let contents = std::fs::read_to_string(dir.join("tracing.log")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(contents.contains("worker-7"), "{contents}");
+        assert!(contents.contains("ThreadId("), "{contents}");
+    }
+
+    #[test]
+    #[cfg(feature = "otlp")]
+    #[serial_test::serial]
+    fn log_trace_ids() {
+        use opentelemetry::trace::TracerProvider;
+        use opentelemetry_sdk::trace::SdkTracerProvider;
+
+        let dir = std::env::temp_dir().join(format!("rust-tracing-ids-{}", std::process::id()));
+        unsafe {
+            std::env::set_var(TRACING_LOG_DIR, &dir);
+            std::env::set_var(TRACING_LOG_ROTATION, "never");
+            std::env::set_var(TRACING_STDOUT_LEVEL, "off");
+            std::env::remove_var(TRACING_LOG_TRACE_IDS);
+        }
+
+        let provider = SdkTracerProvider::builder().build();
+        for format in [LogFormat::Json, LogFormat::Full] {
+            let (fmt, guard) = fmt_layer(format, true);
+            let otel = tracing_opentelemetry::layer().with_tracer(provider.tracer("test"));
+            let registry = tracing_subscriber::registry().with(vec![otel.boxed(), fmt]);
+            tracing::subscriber::with_default(registry, || {
+                tracing::info_span!("work").in_scope(|| tracing::error!("in span"));
+                tracing::error!("outside");
+            });
+            drop(guard);
+        }
+
+        unsafe {
+            std::env::remove_var(TRACING_LOG_DIR);
+            std::env::remove_var(TRACING_LOG_ROTATION);
+            std::env::remove_var(TRACING_STDOUT_LEVEL);
+        }
+        let contents = std::fs::read_to_string(dir.join("tracing.log")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 4, "{contents}");
+        assert!(lines[0].starts_with(r#"{"trace_id":""#), "{contents}");
+        assert!(lines[0].contains(r#""span_id":""#), "{contents}");
+        assert!(lines[0].contains("in span"), "{contents}");
+        assert!(!lines[1].contains("trace_id"), "{contents}");
+        assert!(lines[2].starts_with("trace_id="), "{contents}");
+        assert!(lines[2].contains(" span_id="), "{contents}");
+        assert!(!lines[3].contains("trace_id"), "{contents}");
+    }
+
+    #[test]
+    fn parse_log_rotation() {
+        assert_eq!("daily".parse(), Ok(LogRotation::Daily));
+        assert_eq!("Hourly".parse(), Ok(LogRotation::Hourly));
+        assert_eq!("never".parse(), Ok(LogRotation::Never));
+        assert_eq!(
+            "weekly".parse::<LogRotation>().unwrap_err().to_string(),
+            "invalid log rotation: weekly"
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "otlp"))]
+    fn init_without_otlp() {
+        assert!(init_tracing().otel().is_none());
+        assert!(tracing::dispatcher::has_been_set());
+
+        let guard = init_tracing();
+        assert!(guard.otel().is_none());
+        assert!(!guard.is_file_logging());
+    }
+
+    #[test]
+    #[cfg(feature = "otlp")]
+    #[serial_test::serial]
+    fn reinit_applies_new_config() {
+        const OTEL_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+        unsafe {
+            std::env::set_var(OTEL_ENDPOINT, "http://localhost:4318");
+            std::env::set_var(RUST_LOG, "error");
+        }
+        // Tracing may already have been installed by another test, with or
+        // without OTLP.
+        let guard = reinit_tracing(init_tracing());
+        let otel_installed = RELOAD.get().unwrap().otel.is_some();
+        assert_eq!(guard.otel().is_some(), otel_installed);
+        assert!(!tracing::enabled!(tracing::Level::INFO));
+
+        unsafe { std::env::set_var(RUST_LOG, "info") };
+        let guard = reinit_tracing(guard);
+        assert!(tracing::enabled!(tracing::Level::INFO));
+        if !otel_installed {
+            // OTLP cannot be enabled if it was disabled at init.
+            assert!(guard.otel().is_none());
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+            unsafe {
+                std::env::remove_var(OTEL_ENDPOINT);
+                std::env::remove_var(RUST_LOG);
+            }
+            return;
+        }
+        assert!(guard.otel().is_some());
+        // The OTLP layer shares the directives of the fmt layer.
+        assert!(!tracing::enabled!(tracing::Level::DEBUG));
+
+        unsafe { std::env::remove_var(RUST_LOG) };
+        let guard = reinit_tracing(guard);
+        // Without directives, the OTLP layer exports at DEBUG by default.
+        assert!(tracing::enabled!(tracing::Level::DEBUG));
+
+        unsafe { std::env::remove_var(OTEL_ENDPOINT) };
+        let guard = reinit_tracing(guard);
+        assert!(guard.otel().is_none());
+        assert!(!tracing::enabled!(tracing::Level::DEBUG));
+
+        // Disabled OTLP may be enabled again.
+        unsafe { std::env::set_var(OTEL_ENDPOINT, "http://localhost:4318") };
+        let guard = reinit_tracing(guard);
+        assert!(guard.otel().is_some());
+        unsafe { std::env::remove_var(OTEL_ENDPOINT) };
+        let guard = reinit_tracing(guard);
+        assert!(guard.otel().is_none());
+
+        // Later inits leave the installed tracing in place.
+        unsafe { std::env::set_var(OTEL_ENDPOINT, "http://localhost:4318") };
+        let second = try_init_tracing().unwrap();
+        assert!(second.otel().is_none());
+        assert!(!tracing::enabled!(tracing::Level::DEBUG));
+        unsafe { std::env::remove_var(OTEL_ENDPOINT) };
+        drop(second);
+        assert!(guard.otel().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "otlp")]
+    #[serial_test::serial]
+    fn otlp_layers_only_when_enabled() {
+        const OTEL_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+        unsafe { std::env::remove_var(OTEL_ENDPOINT) };
+        let (disabled, handles, _guard) = layers(Overrides::default());
+        assert_eq!(disabled.len(), 1);
+        assert!(handles.otel.is_none());
+
+        unsafe { std::env::set_var(OTEL_ENDPOINT, "http://localhost:4318") };
+        let (enabled, handles, guard) = layers(Overrides::default());
+        unsafe { std::env::remove_var(OTEL_ENDPOINT) };
+        assert_eq!(enabled.len(), 3);
+        assert!(handles.otel.is_some());
+        assert!(guard.otel().is_some());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn concurrent_init() {
+        let guards: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(try_init_tracing))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        assert!(guards.iter().all(Result::is_ok));
     }
 }
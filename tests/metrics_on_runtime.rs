@@ -0,0 +1,45 @@
+//! Runs in its own process, as it installs the global subscriber and metrics
+//! recorder.
+#![cfg(feature = "metrics-server")]
+
+use std::{
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+    time::Duration,
+};
+
+/// Whether the condition holds within a second.
+fn eventually(f: impl Fn() -> bool) -> bool {
+    (0..100).any(|_| {
+        std::thread::sleep(Duration::from_millis(10));
+        f()
+    })
+}
+
+#[test]
+fn exporter_runs_on_runtime() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    unsafe { std::env::set_var("TRACING_METRICS_PORT", port.to_string()) };
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .unwrap();
+    assert_eq!(runtime.metrics().num_alive_tasks(), 0);
+
+    let _guard = rust_tracing::trace_with_metrics_on_runtime(runtime.handle().clone());
+    assert!(runtime.metrics().num_alive_tasks() > 0);
+    let listening = || TcpStream::connect(("127.0.0.1", port)).is_ok();
+    assert!(eventually(listening));
+
+    // The exporter is a task of the runtime, so it stops with the runtime,
+    // although the guard is still held.
+    runtime.shutdown_background();
+    assert!(eventually(|| !listening()));
+}